@@ -1,19 +1,39 @@
-use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
 use bevy::{
     app::{App, Plugin, Update},
     ecs::system::Res,
 };
+use itertools::Itertools;
+
+use crate::game_bots_plugin::Bot;
+use crate::game_coordinates_utils::{CellCoords, CELL_SIZE};
+use crate::game_setup_data::MapData;
+use crate::ibounds3::IBounds3;
+use crate::input_actions_plugin::{ActionAxes, ButtonAction};
+use crate::visual_bots_plugin::BOT_EYE_OFFSET;
+use crate::visual_picking::SelectedBot;
+use crate::visual_scene_plugin::LevelSlice;
 
 pub struct CameraControllerPlugin;
 
 impl Plugin for CameraControllerPlugin {
     fn build(&self, app: &mut App) {
         _ = app
+            .init_resource::<CameraMode>()
             .add_systems(PostStartup, setup_panning_orbiting_camera)
             .add_systems(
                 Update,
-                (camera_panning_system, camera_orbiting_system).before(update_camera_target),
+                (
+                    camera_panning_system,
+                    camera_orbiting_system,
+                    camera_zoom_system,
+                    camera_selection_system,
+                    camera_framing_system,
+                    camera_level_system,
+                    camera_projection_system,
+                )
+                    .before(update_camera_target),
             )
             .add_systems(Update, update_camera_target);
     }
@@ -45,75 +65,66 @@ impl Default for CameraLooking {
     }
 }
 
+/// Camera target and looking as currently shown, easing towards `CameraTarget` and `CameraLooking`
+#[derive(Component, Debug)]
+pub struct CameraSmoothing {
+    pub target: Vec3,
+    pub look_from: Vec3,
+    pub up: Vec3,
+}
+
+/// How the camera follows the selected bot
+#[derive(Resource, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Orbits the camera target, which tracks the selected bot if any
+    #[default]
+    Orbit,
+    /// Looks through the eyes of the selected bot
+    FirstPerson,
+    /// Looks straight down through an orthographic projection, X right and Y up like the glyph grid, with the
+    /// levels above the camera target sliced off
+    TopDown,
+}
+
 /// Sets up a perspective camera with default parameters
 #[allow(clippy::needless_pass_by_value)]
 fn setup_panning_orbiting_camera(mut commands: Commands, query: Query<(Entity, &Transform), With<Camera>>) {
     for (camera_entity, camera_transform) in &query {
+        let camera_target = CameraTarget::default();
+        let camera_looking = CameraLooking {
+            look_from: camera_transform.translation,
+            up: camera_transform.up().try_normalize().unwrap_or(Vec3::Y),
+        };
         _ = commands.entity(camera_entity).insert((
-            CameraTarget::default(),
-            CameraLooking {
-                look_from: camera_transform.translation,
-                up: camera_transform.up().try_normalize().unwrap_or(Vec3::Y),
+            CameraSmoothing {
+                target: camera_target.target,
+                look_from: camera_looking.look_from,
+                up: camera_looking.up,
             },
+            camera_target,
+            camera_looking,
         ));
     }
 }
 
 const CAMERA_PANNING_SPEED: f32 = 8.;
 const CAMERA_ORBITING_SPEED: f32 = 4.;
+/// How fast the shown camera catches up with its target and looking, higher is snappier
+const CAMERA_SMOOTHING_SHARPNESS: f32 = 8.;
+/// Fraction of the distance zoomed per scroll line
+const CAMERA_ZOOM_SPEED: f32 = 0.1;
+const CAMERA_MIN_DISTANCE: f32 = 1.;
+const CAMERA_MAX_DISTANCE: f32 = 1024.;
+/// Direction the camera looks from when leaving the top-down view, the one it is set up with
+const ORBIT_LOOK_DIRECTION: Vec3 = Vec3::new(1., -1., 2.);
 
 #[allow(clippy::needless_pass_by_value)]
 fn camera_panning_system(
-    time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    // mut keyboard_events: EventReader<KeyboardInput>,
-    mouse_button_input: Res<ButtonInput<MouseButton>>,
-    mut mouse_motion_events: EventReader<MouseMotion>,
+    time: Res<Time<Real>>,
+    action_axes: Res<ActionAxes>,
     mut query: Query<(&mut CameraTarget, &Transform), With<Camera>>,
 ) {
-    let translation = Vec2::ZERO;
-
-    // Keyboard control
-    // // This unfortunately falls under the system key repeat control causing the movement to be jerky (move + pause + move continuesly)
-    // let translation = keyboard_events
-    //     .read()
-    //     .filter_map(
-    //         |keyboard_input| match keyboard_input.state == ButtonState::Pressed {
-    //             true => Some(keyboard_input.key_code),
-    //             false => None,
-    //         },
-    //     )
-    //     .fold(Vec2::ZERO, |acc, key| {
-    //         acc + match key {
-    //             Some(KeyCode::W) => Vec2::new(0., 1.),
-    //             Some(KeyCode::S) => -Vec2::new(0., 1.),
-    //             Some(KeyCode::A) => -Vec2::new(1., 0.),
-    //             Some(KeyCode::D) => Vec2::new(1., 0.),
-    //             _ => Vec2::ZERO,
-    //         }
-    //     });
-    let translation = if keyboard_input.pressed(KeyCode::ShiftLeft) {
-        translation
-    } else {
-        keyboard_input.get_pressed().fold(translation, |acc, &key| {
-            acc + match key {
-                KeyCode::KeyW => Vec2::new(0., 1.),
-                KeyCode::KeyS => -Vec2::new(0., 1.),
-                KeyCode::KeyA => -Vec2::new(1., 0.),
-                KeyCode::KeyD => Vec2::new(1., 0.),
-                _ => Vec2::ZERO,
-            }
-        })
-    };
-
-    // Mouse control
-    let translation = if mouse_button_input.pressed(MouseButton::Middle) {
-        mouse_motion_events.read().fold(translation, |acc, &event| {
-            acc + Vec2::new(-event.delta.x, event.delta.y)
-        })
-    } else {
-        translation
-    };
+    let translation = action_axes.pan;
 
     if translation == Vec2::ZERO {
         return;
@@ -132,39 +143,15 @@ fn camera_panning_system(
 
 #[allow(clippy::needless_pass_by_value)]
 fn camera_orbiting_system(
-    time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mouse_button_input: Res<ButtonInput<MouseButton>>,
-    mut mouse_motion_events: EventReader<MouseMotion>,
+    time: Res<Time<Real>>,
+    action_axes: Res<ActionAxes>,
+    camera_mode: Res<CameraMode>,
     mut query: Query<(&mut CameraLooking, &Transform), With<Camera>>,
 ) {
-    let delta = Vec2::ZERO;
-
-    // Keyboard control
-    let delta = if keyboard_input.pressed(KeyCode::ShiftLeft) {
-        keyboard_input.get_pressed().fold(delta, |acc, &key| {
-            acc + match key {
-                KeyCode::KeyW => Vec2::new(0., 1.),
-                KeyCode::KeyS => -Vec2::new(0., 1.),
-                KeyCode::KeyA => -Vec2::new(1., 0.),
-                KeyCode::KeyD => Vec2::new(1., 0.),
-                _ => Vec2::ZERO,
-            }
-        })
-    } else {
-        delta
-    };
+    let delta = action_axes.orbit;
 
-    // Mouse control
-    let delta = if mouse_button_input.pressed(MouseButton::Right) {
-        mouse_motion_events.read().fold(delta, |acc, &event| {
-            acc + Vec2::new(-event.delta.x, event.delta.y)
-        })
-    } else {
-        delta
-    };
-
-    if delta == Vec2::ZERO {
+    // The top-down view stays aligned with the glyph grid
+    if delta == Vec2::ZERO || *camera_mode == CameraMode::TopDown {
         return;
     }
 
@@ -184,15 +171,349 @@ fn camera_orbiting_system(
     }
 }
 
-/// Update the Camera using the `CameraTarget`
+/// Moves the camera closer or further away from its target, or scales the orthographic view
+#[allow(clippy::needless_pass_by_value)]
+fn camera_zoom_system(
+    action_axes: Res<ActionAxes>,
+    mut query: Query<(&mut CameraLooking, &mut Projection), With<Camera>>,
+) {
+    let scroll = action_axes.zoom;
+
+    if scroll == 0. {
+        return;
+    }
+
+    let zoom_factor = CAMERA_ZOOM_SPEED.mul_add(-scroll, 1.);
+    for (mut camera_looking, mut projection) in &mut query {
+        if let Projection::Orthographic(orthographic) = projection.as_mut() {
+            orthographic.scale =
+                (orthographic.scale * zoom_factor).clamp(CAMERA_MIN_DISTANCE, CAMERA_MAX_DISTANCE);
+            continue;
+        }
+
+        let distance = camera_looking.look_from.length();
+        let zoomed_distance =
+            (distance * zoom_factor).clamp(CAMERA_MIN_DISTANCE, CAMERA_MAX_DISTANCE);
+        camera_looking.look_from = camera_looking.look_from.normalize_or_zero() * zoomed_distance;
+    }
+}
+
+/// Fits the whole map, or the level the camera target is on, into view, keeping the looking direction
+#[allow(clippy::needless_pass_by_value)]
+fn camera_framing_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    map_data: Option<Res<MapData>>,
+    mut selected_bot: ResMut<SelectedBot>,
+    mut query: Query<
+        (
+            &mut CameraTarget,
+            &mut CameraLooking,
+            &mut Projection,
+            &Camera,
+        ),
+        With<Camera>,
+    >,
+) {
+    let Some(map_data) = map_data else {
+        return;
+    };
+    let frame_map = button_actions.just_pressed(ButtonAction::FrameMap);
+    if !frame_map && !button_actions.just_pressed(ButtonAction::FrameLevel) {
+        return;
+    }
+    // Framing takes over from following the selected bot
+    selected_bot.0 = None;
+
+    let bounds = &map_data.bounds;
+    for (mut camera_target, mut camera_looking, mut projection, camera) in &mut query {
+        let framed_bounds = if frame_map {
+            *bounds
+        } else {
+            let level = target_level(camera_target.target, bounds);
+            IBounds3 {
+                min: IVec3::new(bounds.min.x, bounds.min.y, level),
+                max: IVec3::new(bounds.max.x, bounds.max.y, level + 1),
+            }
+        };
+
+        match projection.as_mut() {
+            Projection::Perspective(perspective) => {
+                let (center, distance) = bounds_framing(&framed_bounds, perspective.fov);
+                camera_target.target = center;
+                camera_looking.look_from = camera_looking.look_from.normalize_or_zero()
+                    * distance.clamp(CAMERA_MIN_DISTANCE, CAMERA_MAX_DISTANCE);
+            }
+            Projection::Orthographic(orthographic) => {
+                // Seen from above, the target stays on its level so the slice does not change
+                let aspect_ratio = camera
+                    .logical_viewport_size()
+                    .map_or(1., |size| size.x / size.y);
+                let (center, height) = top_down_framing(&framed_bounds, aspect_ratio);
+                camera_target.target = center.truncate().extend(camera_target.target.z);
+                orthographic.scale = height.clamp(CAMERA_MIN_DISTANCE, CAMERA_MAX_DISTANCE);
+            }
+        }
+    }
+}
+
+/// Level of the camera target in game coordinates, clamped to the map levels
+pub fn target_level(target: Vec3, bounds: &IBounds3) -> i32 {
+    CellCoords::from_game_coordinates(target.round())
+        .as_ivec3()
+        .z
+        .clamp(bounds.min.z, bounds.max.z - 1)
+}
+
+/// Center of the bounds in game coordinates and the camera distance fitting them in the vertical field of
+/// view
+fn bounds_framing(bounds: &IBounds3, fov: f32) -> (Vec3, f32) {
+    let size = bounds.size().as_vec3() * CELL_SIZE;
+    let min = CellCoords::from_ivec3(bounds.min).as_game_coordinates() - CELL_SIZE / 2.;
+    let radius = size.length() / 2.;
+
+    (min + size / 2., radius / (fov / 2.).sin())
+}
+
+/// Center of the bounds in game coordinates and the orthographic view height fitting them seen from above
+fn top_down_framing(bounds: &IBounds3, aspect_ratio: f32) -> (Vec3, f32) {
+    let size = bounds.size().as_vec3() * CELL_SIZE;
+    let min = CellCoords::from_ivec3(bounds.min).as_game_coordinates() - CELL_SIZE / 2.;
+
+    (min + size / 2., size.y.max(size.x / aspect_ratio))
+}
+
+/// Moves the camera target a level up or down, and slices off the levels above it in the top-down view
+#[allow(clippy::needless_pass_by_value)]
+fn camera_level_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    camera_mode: Res<CameraMode>,
+    map_data: Option<Res<MapData>>,
+    mut selected_bot: ResMut<SelectedBot>,
+    mut level_slice: ResMut<LevelSlice>,
+    mut query: Query<&mut CameraTarget, With<Camera>>,
+) {
+    let Some(map_data) = map_data else {
+        return;
+    };
+    let bounds = &map_data.bounds;
+    let level_delta = i32::from(button_actions.just_pressed(ButtonAction::LevelUp))
+        - i32::from(button_actions.just_pressed(ButtonAction::LevelDown));
+
+    for mut camera_target in &mut query {
+        if level_delta != 0 {
+            // Changing level takes over from following the selected bot
+            selected_bot.0 = None;
+            let level = (target_level(camera_target.target, bounds) + level_delta)
+                .clamp(bounds.min.z, bounds.max.z - 1);
+            camera_target.target.z = CellCoords::from_ivec3(IVec3::new(0, 0, level))
+                .as_game_coordinates()
+                .z;
+        }
+
+        let slice_level = (*camera_mode == CameraMode::TopDown)
+            .then(|| target_level(camera_target.target, bounds) - bounds.min.z);
+        if level_slice.0 != slice_level {
+            level_slice.0 = slice_level;
+        }
+    }
+}
+
+/// Switches to the orthographic projection looking straight down in the top-down view, and back to the
+/// perspective projection, keeping about the same part of the map in view
+#[allow(clippy::needless_pass_by_value)]
+fn camera_projection_system(
+    camera_mode: Res<CameraMode>,
+    mut query: Query<(&mut CameraLooking, &mut Projection), With<Camera>>,
+) {
+    if !camera_mode.is_changed() {
+        return;
+    }
+
+    for (mut camera_looking, mut projection) in &mut query {
+        let top_down = *camera_mode == CameraMode::TopDown;
+        match projection.as_mut() {
+            Projection::Perspective(perspective) if top_down => {
+                let distance = camera_looking.look_from.length();
+                *projection = Projection::Orthographic(OrthographicProjection {
+                    scaling_mode: ScalingMode::FixedVertical(1.),
+                    scale: view_height(distance, perspective.fov),
+                    ..default()
+                });
+                camera_looking.look_from = Vec3::Z * distance;
+                camera_looking.up = Vec3::Y;
+            }
+            Projection::Orthographic(orthographic) if !top_down => {
+                let perspective = PerspectiveProjection::default();
+                let distance = view_distance(orthographic.scale, perspective.fov)
+                    .clamp(CAMERA_MIN_DISTANCE, CAMERA_MAX_DISTANCE);
+                *projection = Projection::Perspective(perspective);
+                camera_looking.look_from = ORBIT_LOOK_DIRECTION.normalize() * distance;
+                camera_looking.up = Vec3::Z;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Height of the view at the given distance from a perspective camera
+fn view_height(distance: f32, fov: f32) -> f32 {
+    2. * distance * (fov / 2.).tan()
+}
+
+/// Distance from a perspective camera at which the view has the given height
+fn view_distance(height: f32, fov: f32) -> f32 {
+    height / (2. * (fov / 2.).tan())
+}
+
+/// Cycles the selected bot, clears the selection and switches to and from the first person and top-down views
+#[allow(clippy::needless_pass_by_value)]
+fn camera_selection_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    bots_query: Query<Entity, With<Bot>>,
+    mut selected_bot: ResMut<SelectedBot>,
+    mut camera_mode: ResMut<CameraMode>,
+) {
+    if button_actions.just_pressed(ButtonAction::CycleBots) {
+        let bots = bots_query.iter().sorted().collect_vec();
+        let next_idx = selected_bot
+            .0
+            .and_then(|selected| bots.iter().position(|&bot| bot == selected))
+            .map_or(0, |idx| idx + 1);
+        selected_bot.0 = bots.get(next_idx % bots.len().max(1)).copied();
+    }
+    if button_actions.just_pressed(ButtonAction::ClearSelection) {
+        selected_bot.0 = None;
+    }
+    if button_actions.just_pressed(ButtonAction::FirstPerson) {
+        *camera_mode = match *camera_mode {
+            CameraMode::Orbit | CameraMode::TopDown => CameraMode::FirstPerson,
+            CameraMode::FirstPerson => CameraMode::Orbit,
+        };
+    }
+    if button_actions.just_pressed(ButtonAction::TopDown) {
+        *camera_mode = match *camera_mode {
+            CameraMode::Orbit | CameraMode::FirstPerson => CameraMode::TopDown,
+            CameraMode::TopDown => CameraMode::Orbit,
+        };
+    }
+}
+
+/// Update the Camera using the `CameraTarget`, tracking the selected bot and smoothing every move, in real time
+/// so the camera keeps moving while the simulation is paused
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::type_complexity)]
 fn update_camera_target(
-    mut query: Query<(&mut Transform, &CameraTarget, &CameraLooking), With<Camera>>,
+    time: Res<Time<Real>>,
+    selected_bot: Res<SelectedBot>,
+    camera_mode: Res<CameraMode>,
+    bots_query: Query<&Transform, (With<Bot>, Without<Camera>)>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut CameraTarget,
+            &CameraLooking,
+            &mut CameraSmoothing,
+        ),
+        With<Camera>,
+    >,
 ) {
-    for (mut transform, camera_target, camera_looking) in &mut query {
-        let camera_locations = camera_target.target + camera_looking.look_from;
-        let camera_looking_to = -camera_looking.look_from;
-        let camera_up = camera_looking.up;
+    let selected_bot_transform = selected_bot
+        .0
+        .and_then(|entity| bots_query.get(entity).ok());
+
+    // Frame rate independent exponential smoothing
+    let smoothing = 1. - (-CAMERA_SMOOTHING_SHARPNESS * time.delta_seconds()).exp();
+
+    for (mut transform, mut camera_target, camera_looking, mut camera_smoothing) in &mut query {
+        if let Some(bot_transform) = selected_bot_transform {
+            if *camera_mode == CameraMode::FirstPerson {
+                *transform =
+                    Transform::from_translation(bot_transform.transform_point(BOT_EYE_OFFSET))
+                        .looking_to(*bot_transform.forward(), *bot_transform.up());
+                continue;
+            }
+
+            camera_target.target = bot_transform.translation;
+        }
+
+        camera_smoothing.target = camera_smoothing
+            .target
+            .lerp(camera_target.target, smoothing);
+        camera_smoothing.look_from = smoothed_look_from(
+            camera_smoothing.look_from,
+            camera_looking.look_from,
+            smoothing,
+        );
+        camera_smoothing.up = camera_smoothing
+            .up
+            .lerp(camera_looking.up, smoothing)
+            .try_normalize()
+            .unwrap_or(camera_looking.up);
+
+        let camera_locations = camera_smoothing.target + camera_smoothing.look_from;
+        let camera_looking_to = -camera_smoothing.look_from;
+        let camera_up = camera_smoothing.up;
         *transform =
             Transform::from_translation(camera_locations).looking_to(camera_looking_to, camera_up);
     }
 }
+
+/// Turns and scales the looking vector towards the desired one, so orbiting keeps the distance while easing
+fn smoothed_look_from(look_from: Vec3, desired_look_from: Vec3, smoothing: f32) -> Vec3 {
+    let (Some(direction), Some(desired_direction)) =
+        (look_from.try_normalize(), desired_look_from.try_normalize())
+    else {
+        return desired_look_from;
+    };
+
+    let rotation = Quat::IDENTITY.slerp(
+        Quat::from_rotation_arc(direction, desired_direction),
+        smoothing,
+    );
+    let distance =
+        (desired_look_from.length() - look_from.length()).mul_add(smoothing, look_from.length());
+    rotation * direction * distance
+}
+
+#[cfg(test)]
+mod tests {
+    use core::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn test_bounds_framing() {
+        let bounds = IBounds3::new(IVec3::ZERO, IVec3::new(4, 4, 2));
+
+        let (center, distance) = bounds_framing(&bounds, FRAC_PI_2);
+
+        assert_eq!(center, Vec3::new(-0.5, -0.5, -0.5));
+        assert!((distance - 18_f32.sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_top_down_framing() {
+        let bounds = IBounds3::new(IVec3::ZERO, IVec3::new(8, 2, 2));
+
+        let (center, height) = top_down_framing(&bounds, 2.);
+
+        assert_eq!(center, Vec3::new(-0.5, -0.5, -0.5));
+        assert!((height - 4.).abs() < 1e-4);
+        assert!((top_down_framing(&bounds, 8.).1 - 2.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_view_height_and_distance() {
+        assert!((view_height(4., FRAC_PI_2) - 8.).abs() < 1e-4);
+        assert!((view_distance(view_height(3., 0.8), 0.8) - 3.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_smoothed_look_from_keeps_distance() {
+        let look_from = smoothed_look_from(Vec3::X * 4., Vec3::Y * 4., 0.5);
+
+        assert!((look_from.length() - 4.).abs() < 1e-4);
+        assert!((look_from.x - look_from.y).abs() < 1e-4);
+        assert!(smoothed_look_from(Vec3::X, Vec3::Y * 2., 1.).abs_diff_eq(Vec3::Y * 2., 1e-4));
+    }
+}
@@ -0,0 +1,471 @@
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+
+use crate::game_cells_plugin::{cell, Cells};
+use crate::game_coordinates_utils::{CellCoords, CELL_SIZE};
+use crate::game_doors_plugin::DoorPlacements;
+use crate::game_items_plugin::ItemPlacements;
+use crate::game_map_file::{MapFile, MapInfo};
+use crate::game_physics_layers::Layer;
+use crate::game_raid_plugin::CaptureCells;
+use crate::game_scene_plugin::CellsChangedEvent;
+use crate::game_setup_data::MapData;
+use crate::game_teams_plugin::SpawnPoints;
+use crate::ibounds3::IBounds3;
+use crate::input_actions_plugin::ButtonAction;
+use crate::visual_picking::CursorRay;
+
+const EDITOR_FACE_COLOR: Color = Color::YELLOW;
+const EDITOR_PICK_DISTANCE: f32 = 1000.;
+/// Depth the picked point is pushed into the hit wall, so the point lies on the side of the face it belongs to
+const EDITOR_PICK_DEPTH: f32 = 0.01;
+
+/// Map editor toggled at runtime: clicking a cell face on the edited level opens or closes it
+#[derive(Debug)]
+pub struct EditorPlugin {
+    /// RON map file the edited map is saved to
+    pub save_path: PathBuf,
+}
+
+impl Default for EditorPlugin {
+    fn default() -> Self {
+        Self {
+            save_path: PathBuf::from("assets/maps/edited.ron"),
+        }
+    }
+}
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app
+            .insert_resource(MapEditor {
+                save_path: self.save_path.clone(),
+                ..default()
+            })
+            .add_systems(
+                Update,
+                (
+                    editor_toggle_system,
+                    (
+                        editor_level_system,
+                        editor_history_system,
+                        editor_face_system,
+                        editor_save_system,
+                    )
+                        .run_if(editor_enabled),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// A cell changed by the editor, with its type before and after the edit
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CellEdit {
+    pub cell_indices: IVec3,
+    pub before: cell::Type,
+    pub after: cell::Type,
+}
+
+/// The cells changed by opening or closing a face. Undoing restores their previous types, so a face that was
+/// open on one side only is restored as such.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FaceEdit {
+    pub cells: Vec<CellEdit>,
+}
+
+impl FaceEdit {
+    /// Opens or closes the face of the cell in the move direction and records the cells it changed
+    pub fn apply_new(
+        cells: &mut Cells,
+        cell_indices: IVec3,
+        move_direction: IVec3,
+        open: bool,
+    ) -> Self {
+        let before = [cell_indices, cell_indices + move_direction]
+            .map(|indices| (indices, cells.get(indices).copied()));
+        let changed = cells.set_face_open(cell_indices, move_direction, open);
+
+        Self {
+            cells: before
+                .into_iter()
+                .filter(|(indices, _)| changed.contains(indices))
+                .filter_map(|(indices, before)| {
+                    Some(CellEdit {
+                        cell_indices: indices,
+                        before: before?,
+                        after: *cells.get(indices)?,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn reverted(&self) -> Self {
+        Self {
+            cells: self
+                .cells
+                .iter()
+                .map(|cell_edit| CellEdit {
+                    before: cell_edit.after,
+                    after: cell_edit.before,
+                    ..*cell_edit
+                })
+                .collect(),
+        }
+    }
+
+    /// Sets the cells to their type after the edit, returns the indices of the cells that changed
+    fn apply(&self, cells: &mut Cells) -> Vec<IVec3> {
+        self.cells
+            .iter()
+            .filter_map(|cell_edit| {
+                let cell_type = cells.get_mut(cell_edit.cell_indices)?;
+                (*cell_type != cell_edit.after).then(|| {
+                    *cell_type = cell_edit.after;
+                    cell_edit.cell_indices
+                })
+            })
+            .collect()
+    }
+}
+
+/// Undo and redo stacks of the face edits
+#[derive(Debug, Default)]
+pub struct EditHistory {
+    undo: Vec<FaceEdit>,
+    redo: Vec<FaceEdit>,
+}
+
+impl EditHistory {
+    /// Records a new edit, anything undone before can not be redone anymore
+    pub fn push(&mut self, edit: FaceEdit) {
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    /// Returns the edit to apply to undo the last edit
+    pub fn undo(&mut self) -> Option<FaceEdit> {
+        let edit = self.undo.pop()?;
+        let reverted = edit.reverted();
+        self.redo.push(edit);
+        Some(reverted)
+    }
+
+    /// Returns the last undone edit to apply again
+    pub fn redo(&mut self) -> Option<FaceEdit> {
+        let edit = self.redo.pop()?;
+        self.undo.push(edit.clone());
+        Some(edit)
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct MapEditor {
+    pub enabled: bool,
+    /// Cell indices level being edited
+    pub level: i32,
+    pub history: EditHistory,
+    pub save_path: PathBuf,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn editor_enabled(map_editor: Res<MapEditor>) -> bool {
+    map_editor.enabled
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn editor_toggle_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    mut map_editor: ResMut<MapEditor>,
+) {
+    if button_actions.just_pressed(ButtonAction::EditorToggle) {
+        map_editor.enabled = !map_editor.enabled;
+        info!(
+            "Map editor {}",
+            if map_editor.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn editor_level_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    cells: Option<Res<Cells>>,
+    mut map_editor: ResMut<MapEditor>,
+) {
+    let Some(cells) = cells else {
+        return;
+    };
+
+    let level_delta = i32::from(button_actions.just_pressed(ButtonAction::EditorLevelUp))
+        - i32::from(button_actions.just_pressed(ButtonAction::EditorLevelDown));
+    if level_delta != 0 {
+        map_editor.level = (map_editor.level + level_delta).clamp(0, (cells.size.z - 1).max(0));
+        info!("Map editor level {}", map_editor.level);
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn editor_history_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    cells: Option<ResMut<Cells>>,
+    mut map_editor: ResMut<MapEditor>,
+    mut cells_changed_writer: EventWriter<CellsChangedEvent>,
+) {
+    let Some(mut cells) = cells else {
+        return;
+    };
+    let edit = if button_actions.just_pressed(ButtonAction::Redo) {
+        map_editor.history.redo()
+    } else if button_actions.just_pressed(ButtonAction::Undo) {
+        map_editor.history.undo()
+    } else {
+        None
+    };
+
+    if let Some(edit) = edit {
+        apply_face_edit(&mut cells, &edit, &mut cells_changed_writer);
+    }
+}
+
+/// Highlights the face of the wall or floor under the cursor on the edited level and toggles it on click
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)]
+fn editor_face_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    cursor_ray: CursorRay,
+    spatial_query: SpatialQuery,
+    cells: Option<ResMut<Cells>>,
+    map_data: Option<Res<MapData>>,
+    mut map_editor: ResMut<MapEditor>,
+    mut cells_changed_writer: EventWriter<CellsChangedEvent>,
+    mut gizmos: Gizmos,
+) {
+    let (Some(mut cells), Some(map_data)) = (cells, map_data) else {
+        return;
+    };
+    let Some(ray) = cursor_ray.ray() else {
+        return;
+    };
+
+    // Walls and floors of the levels above the edited one may be in the way, the nearest hit on the edited level
+    // is picked. A closed face is picked on its wall, an open one on the floor next to it.
+    #[allow(clippy::cast_sign_loss)]
+    let max_hits = cells.size.z.max(1) as u32;
+    let Some((cell_indices, move_direction)) = spatial_query
+        .ray_hits(
+            ray.origin,
+            ray.direction,
+            EDITOR_PICK_DISTANCE,
+            max_hits,
+            true,
+            SpatialQueryFilter::from_mask(Layer::Constructed),
+        )
+        .into_iter()
+        .map(|hit| {
+            let point = ray.get_point(hit.time_of_impact) - hit.normal * EDITOR_PICK_DEPTH;
+            (hit.time_of_impact, face_at_point(point, &map_data.bounds))
+        })
+        .filter(|(_, (cell_indices, _))| cell_indices.z == map_editor.level)
+        .min_by(|(distance_a, _), (distance_b, _)| distance_a.total_cmp(distance_b))
+        .map(|(_, face)| face)
+    else {
+        return;
+    };
+    if cells.get(cell_indices).is_none() {
+        return;
+    }
+
+    let face_center = CellCoords::from_cell_indices(cell_indices, &map_data.bounds)
+        .as_game_coordinates()
+        + move_direction.as_vec3() * (CELL_SIZE / 2.);
+    gizmos.rect(
+        face_center,
+        Quat::from_rotation_arc(Vec3::Z, move_direction.as_vec3()),
+        Vec2::splat(CELL_SIZE),
+        EDITOR_FACE_COLOR,
+    );
+
+    if button_actions.just_pressed(ButtonAction::Select) {
+        let open = !cells.is_face_open(cell_indices, move_direction);
+        let edit = FaceEdit::apply_new(&mut cells, cell_indices, move_direction, open);
+        if !edit.cells.is_empty() {
+            _ = cells_changed_writer.send(CellsChangedEvent {
+                cell_indices: edit
+                    .cells
+                    .iter()
+                    .map(|cell_edit| cell_edit.cell_indices)
+                    .collect(),
+            });
+            map_editor.history.push(edit);
+        }
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)]
+fn editor_save_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    map_editor: Res<MapEditor>,
+    cells: Option<Res<Cells>>,
+    map_data: Option<Res<MapData>>,
+    map_info: Option<Res<MapInfo>>,
+    spawn_points: Option<Res<SpawnPoints>>,
+    item_placements: Option<Res<ItemPlacements>>,
+    capture_cells: Option<Res<CaptureCells>>,
+    door_placements: Option<Res<DoorPlacements>>,
+) {
+    if !button_actions.just_pressed(ButtonAction::EditorSave) {
+        return;
+    }
+    let (
+        Some(cells),
+        Some(map_data),
+        Some(map_info),
+        Some(spawn_points),
+        Some(item_placements),
+        Some(capture_cells),
+        Some(door_placements),
+    ) = (
+        cells,
+        map_data,
+        map_info,
+        spawn_points,
+        item_placements,
+        capture_cells,
+        door_placements,
+    )
+    else {
+        return;
+    };
+
+    let map_file = MapFile::from_spawned_map(
+        &cells,
+        &map_data,
+        &map_info,
+        &spawn_points,
+        &item_placements,
+        &capture_cells,
+        &door_placements,
+    );
+    let saved = map_file.to_ron_string().and_then(|ron_string| {
+        fs::write(&map_editor.save_path, ron_string).map_err(|error| error.to_string())
+    });
+    match saved {
+        Ok(()) => info!("Map saved to {}", map_editor.save_path.display()),
+        Err(error) => error!(
+            "Failed to save map to {}: {error}",
+            map_editor.save_path.display()
+        ),
+    }
+}
+
+fn apply_face_edit(
+    cells: &mut Cells,
+    edit: &FaceEdit,
+    cells_changed_writer: &mut EventWriter<CellsChangedEvent>,
+) {
+    let cell_indices = edit.apply(cells);
+    if !cell_indices.is_empty() {
+        _ = cells_changed_writer.send(CellsChangedEvent { cell_indices });
+    }
+}
+
+/// Returns the cell containing the point and the direction of its face nearest to the point
+fn face_at_point(point: Vec3, bounds: &IBounds3) -> (IVec3, IVec3) {
+    let cell_center = (point / CELL_SIZE).round();
+    let offset = point - cell_center * CELL_SIZE;
+    let move_direction = if offset.x.abs() >= offset.y.abs() {
+        IVec3::new(if offset.x < 0. { -1 } else { 1 }, 0, 0)
+    } else {
+        IVec3::new(0, if offset.y < 0. { -1 } else { 1 }, 0)
+    };
+
+    (cell_center.as_ivec3() - bounds.min, move_direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_face_at_point() {
+        let bounds = IBounds3::new(IVec3::ZERO, IVec3::new(4, 4, 1));
+
+        assert_eq!(
+            face_at_point(Vec3::new(0.4, 0.1, 0.), &bounds),
+            (IVec3::new(2, 2, 0), IVec3::X)
+        );
+        assert_eq!(
+            face_at_point(Vec3::new(0.6, 0.1, 0.), &bounds),
+            (IVec3::new(3, 2, 0), IVec3::NEG_X)
+        );
+        assert_eq!(
+            face_at_point(Vec3::new(-1.1, -1.45, 0.), &bounds),
+            (IVec3::new(1, 1, 0), IVec3::NEG_Y)
+        );
+    }
+
+    #[test]
+    fn test_edit_history() {
+        let edit = |x, before, after| FaceEdit {
+            cells: vec![CellEdit {
+                cell_indices: IVec3::new(x, 0, 0),
+                before,
+                after,
+            }],
+        };
+        let (closed, open) = (cell::EMPTY, cell::OPEN_POS_X);
+        let mut history = EditHistory::default();
+
+        history.push(edit(0, closed, open));
+        history.push(edit(1, open, closed));
+
+        assert_eq!(history.undo(), Some(edit(1, closed, open)));
+        assert_eq!(history.redo(), Some(edit(1, open, closed)));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(edit(1, closed, open)));
+        assert_eq!(history.undo(), Some(edit(0, open, closed)));
+        assert_eq!(history.undo(), None);
+
+        // A new edit drops whatever was undone
+        history.push(edit(2, closed, open));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(edit(2, open, closed)));
+        assert_eq!(history.redo(), Some(edit(2, closed, open)));
+    }
+
+    #[test]
+    fn test_undo_half_open_face() {
+        // The face between the two cells is open on the side of the first cell only
+        let mut cells = Cells::closed(IVec3::new(2, 1, 1));
+        if let Some(cell_type) = cells.get_mut(IVec3::ZERO) {
+            *cell_type = cell_type.with_open(cell::OPEN_POS_X);
+        }
+        let cell_types = |cells: &Cells| {
+            (
+                cells.get(IVec3::ZERO).copied(),
+                cells.get(IVec3::X).copied(),
+            )
+        };
+        let initial_cell_types = cell_types(&cells);
+
+        let mut history = EditHistory::default();
+        history.push(FaceEdit::apply_new(&mut cells, IVec3::ZERO, IVec3::X, true));
+        assert!(cells.is_face_open(IVec3::X, IVec3::NEG_X));
+
+        assert_eq!(
+            history.undo().map(|edit| edit.apply(&mut cells)),
+            Some(vec![IVec3::X])
+        );
+        assert_eq!(cell_types(&cells), initial_cell_types);
+    }
+}
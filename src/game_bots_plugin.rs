@@ -2,17 +2,21 @@ use core::f32::consts::FRAC_PI_2;
 use core::time::Duration;
 
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy_prng::WyRand;
 use bevy_rand::resource::GlobalEntropy;
-use bevy_xpbd_3d::components::{CollisionLayers, RigidBody};
+use bevy_xpbd_3d::components::RigidBody;
 use bevy_xpbd_3d::plugins::collision::Collider;
 use bevy_xpbd_3d::prelude::*;
-use rand_core::RngCore;
+use serde::{Deserialize, Serialize};
 
-use crate::game_cells_plugin::{cell, Cells};
+use crate::game_cells_plugin::Cells;
 use crate::game_coordinates_utils::CellCoords;
-use crate::game_physics_layers::Layer;
+use crate::game_items_plugin::Inventory;
+use crate::game_nav_plugin::{NavGraph, NavTarget};
+use crate::game_scene_plugin::SkipStartupSpawn;
 use crate::game_setup_data::MapData;
+use crate::game_teams_plugin::{SpawnPoints, Team, TeamCollisionRules};
 use crate::ibounds3::IBounds3;
 
 const BOT_SPAWNING_INTERVAL: f32 = 0.5;
@@ -28,34 +32,108 @@ impl Plugin for BotsPlugin {
     fn build(&self, app: &mut App) {
         _ = app
             .add_event::<BotSpawnedEvent>()
-            .init_resource::<BotSpawnerTimer>()
+            .init_resource::<BotSpawner>()
             .init_resource::<BotLogicUpdateTimer>()
+            .init_resource::<TeamPrograms>()
             // .add_systems(Startup, bots_startup)
-            .add_systems(Update, (bots_spawning_system, bots_movement_system));
+            .add_systems(
+                Update,
+                (
+                    bots_spawning_system.run_if(not(resource_exists::<SkipStartupSpawn>)),
+                    bots_movement_system,
+                ),
+            );
     }
 }
 
 #[derive(Component, Debug)]
 pub struct Bot {}
 
+/// Navigation logic a bot runs every logic update
+#[derive(Component, Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BotProgram {
+    /// Drives straight ahead and brakes in front of walls
+    Level0,
+    /// Turns right, back or left whenever the way ahead is blocked
+    Level1,
+    /// Takes the first open direction out of every cell, preferring forward, right, left then back
+    Level2,
+    /// Follows its `NavTarget` along the shortest path, runs `Level2` while it has none
+    #[default]
+    Navigator,
+}
+
+impl BotProgram {
+    pub const ALL: [Self; 4] = [Self::Level0, Self::Level1, Self::Level2, Self::Navigator];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Level0 => "level0",
+            Self::Level1 => "level1",
+            Self::Level2 => "level2",
+            Self::Navigator => "navigator",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|program| program.name() == name)
+    }
+}
+
+/// Program run by the bots of each team, teams without an entry run the default program
+#[derive(Resource, Debug, Default)]
+pub struct TeamPrograms {
+    pub programs: HashMap<Team, BotProgram>,
+}
+
+impl TeamPrograms {
+    pub fn program(&self, team: Team) -> BotProgram {
+        self.programs.get(&team).copied().unwrap_or_default()
+    }
+}
+
+/// What a bot decided on its last logic update, kept for debugging
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BotLogicState {
+    /// Logic updates run since the bot spawned
+    pub ticks: u32,
+    /// Move directions probed forward, right, left and back before the navigation turned the bot, and whether the
+    /// face that way is open
+    pub probes: [(IVec3, bool); 4],
+    /// Cell the bot drives into next, if it is moving
+    pub next_cell_indices: Option<IVec3>,
+    pub holding: bool,
+}
+
 #[derive(Event, Debug)]
 // Define an event to represent the spawning of a bot
 pub struct BotSpawnedEvent {
     pub entity: Entity,
+    #[allow(dead_code)]
     pub transform: Transform,
+    pub team: Team,
 }
 
-#[derive(Resource, Debug)]
-struct BotSpawnerTimer(Timer);
+/// Spawns a bot on the next spawn point every time its timer finishes
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct BotSpawner {
+    pub timer: Timer,
+    /// Bots spawned so far, also the index of the next spawn point
+    pub spawned: usize,
+}
 
-impl Default for BotSpawnerTimer {
+impl Default for BotSpawner {
     fn default() -> Self {
-        Self(Timer::from_seconds(BOT_SPAWNING_INTERVAL, TimerMode::Once))
+        Self {
+            timer: Timer::from_seconds(BOT_SPAWNING_INTERVAL, TimerMode::Repeating),
+            spawned: 0,
+        }
     }
 }
 
-#[derive(Resource, Debug)]
-struct BotLogicUpdateTimer(Timer);
+/// Beat every bot runs its program on
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct BotLogicUpdateTimer(pub Timer);
 
 impl Default for BotLogicUpdateTimer {
     fn default() -> Self {
@@ -71,18 +149,51 @@ impl Default for BotLogicUpdateTimer {
 // }
 
 #[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)]
 fn bots_spawning_system(
     time: Res<Time>,
     commands: Commands,
     map_data: Res<MapData>,
-    mut bot_spawner_timer: ResMut<BotSpawnerTimer>,
+    spawn_points: Option<Res<SpawnPoints>>,
+    collision_rules: Res<TeamCollisionRules>,
+    team_programs: Res<TeamPrograms>,
+    mut bot_spawner: ResMut<BotSpawner>,
     bot_spawned_writer: EventWriter<BotSpawnedEvent>,
     rng: ResMut<GlobalEntropy<WyRand>>,
 ) {
     // Update the timer with the time elapsed since the last update
-    if timer_just_finishes(time.delta(), &mut bot_spawner_timer.0) {
-        // Timer has finished, so spawn a new bot
-        spawn_bot_on_map_trigger_event(commands, &map_data.bounds, bot_spawned_writer, rng);
+    if timer_just_finishes(time.delta(), &mut bot_spawner.timer) {
+        // Timer has finished, so spawn a new bot on the next free spawn point
+        let spawn_point = match spawn_points {
+            Some(spawn_points) if !spawn_points.spawn_points.is_empty() => spawn_points
+                .spawn_points
+                .get(bot_spawner.spawned)
+                .map(|&(cell_indices, team)| {
+                    (
+                        CellCoords::from_cell_indices(cell_indices, &map_data.bounds),
+                        team,
+                    )
+                }),
+            // Maps without spawn points get a single bot
+            _ => (bot_spawner.spawned == 0).then(|| {
+                (
+                    generate_random_cell_coords(rng, &map_data.bounds),
+                    Team::Blue,
+                )
+            }),
+        };
+
+        if let Some((cell_coords, team)) = spawn_point {
+            spawn_bot_on_map_trigger_event(
+                commands,
+                cell_coords,
+                team,
+                team_programs.program(team),
+                &collision_rules,
+                bot_spawned_writer,
+            );
+            bot_spawner.spawned += 1;
+        }
     }
 }
 
@@ -92,32 +203,37 @@ fn timer_just_finishes(time_delta: Duration, timer: &mut Timer) -> bool {
 
 fn spawn_bot_on_map_trigger_event(
     commands: Commands,
-    map_bounds: &IBounds3,
+    cell_coords: CellCoords,
+    team: Team,
+    program: BotProgram,
+    collision_rules: &TeamCollisionRules,
     mut bot_spawned_writer: EventWriter<BotSpawnedEvent>,
-    rng: ResMut<GlobalEntropy<WyRand>>,
 ) {
-    let (transfrom, bot_entity) = spawn_bot_on_map(commands, map_bounds, rng);
+    let (transfrom, bot_entity) =
+        spawn_bot_on_map(commands, cell_coords, team, program, collision_rules);
 
     _ = bot_spawned_writer.send(BotSpawnedEvent {
         entity: bot_entity,
         transform: transfrom,
+        team,
     });
 }
 
 fn spawn_bot_on_map(
     commands: Commands,
-    map_bounds: &IBounds3,
-    rng: ResMut<GlobalEntropy<WyRand>>,
+    cell_coords: CellCoords,
+    team: Team,
+    program: BotProgram,
+    collision_rules: &TeamCollisionRules,
 ) -> (Transform, Entity) {
-    let cell_coords = generate_random_cell_coords(rng, map_bounds);
     let transfrom = cell_coords.as_game_coordinates_transform();
-    let bot_entity = spawn_bot_with_transform(commands, transfrom);
+    let bot_entity = spawn_bot_with_transform(commands, transfrom, team, program, collision_rules);
     (transfrom, bot_entity)
 }
 
 #[allow(clippy::cast_possible_wrap)]
 fn generate_random_cell_coords(
-    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    _rng: ResMut<GlobalEntropy<WyRand>>,
     map_bounds: &IBounds3,
 ) -> CellCoords {
     let size = map_bounds.size().as_uvec3();
@@ -127,52 +243,107 @@ fn generate_random_cell_coords(
     CellCoords::from_ivec3(random_coords_limited.as_ivec3() + map_bounds.min)
 }
 
-fn spawn_bot_with_transform(mut commands: Commands, transform: Transform) -> Entity {
+pub fn spawn_bot_with_transform(
+    mut commands: Commands,
+    transform: Transform,
+    team: Team,
+    program: BotProgram,
+    collision_rules: &TeamCollisionRules,
+) -> Entity {
     let collider = Collider::cylinder(0.2, 0.25);
     let bot_entity = commands
         .spawn((
             Bot {},
+            team,
+            program,
+            BotLogicState::default(),
+            Inventory::default(),
             SpatialBundle::from_transform(transform),
             RigidBody::Dynamic,
             MassPropertiesBundle::new_computed(&collider, BOT_MASS_DENSITY_SCALE),
             collider,
-            CollisionLayers::new([Layer::Bots], [Layer::Ground, Layer::Constructed]), // Bots collides with ground, and constructed layers
+            team.bot_collision_layers(collision_rules),
             Friction::new(0.1),
             Restitution::new(0.0).with_combine_rule(CoefficientCombine::Multiply),
             LinearDamping(0.2),
             AngularDamping(0.2),
+            // Bots drive on the level floor, collisions should never lift or tip them over
+            LockedAxes::new()
+                .lock_translation_z()
+                .lock_rotation_x()
+                .lock_rotation_y(),
         ))
         .id();
     bot_entity
 }
 
 #[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::type_complexity)]
 fn bots_movement_system(
     time: Res<Time>,
     cells: Res<Cells>,
     map_data: Res<MapData>,
     mut commands: Commands,
     mut bot_logic_update_timer: ResMut<BotLogicUpdateTimer>,
+    nav_graph: Res<NavGraph>,
     mut query: Query<
         (
             &mut Transform,
             &mut LinearVelocity,
             &mut AngularVelocity,
             Entity,
+            &BotProgram,
+            Option<&NavTarget>,
+            &mut BotLogicState,
         ),
         With<Bot>,
     >,
 ) {
-    for (mut transform, mut linear_velocity, mut angular_velocity, entity) in &mut query {
-        if timer_just_finishes(time.delta(), &mut bot_logic_update_timer.0) {
-            update_brakes_level0_component(
-                *transform,
-                &mut linear_velocity,
-                &mut angular_velocity,
-                &cells,
+    // Tick once per update, not once per bot, so every bot runs its logic on the same beat
+    if !timer_just_finishes(time.delta(), &mut bot_logic_update_timer.0) {
+        return;
+    }
+
+    for (
+        mut transform,
+        mut linear_velocity,
+        mut angular_velocity,
+        entity,
+        program,
+        nav_target,
+        mut logic_state,
+    ) in &mut query
+    {
+        // Probed before the navigation turns the bot, as its program sees them
+        let probes = probe_faces(*transform, &cells, &map_data.bounds);
+        update_brakes_level0_component(
+            *transform,
+            &mut linear_velocity,
+            &mut angular_velocity,
+            &cells,
+            &map_data,
+        );
+        let hold_position = match (program, nav_target) {
+            (BotProgram::Navigator, Some(nav_target)) => update_navigation_target_component(
+                &mut transform,
+                nav_target,
+                &nav_graph,
                 &map_data,
-            );
-            update_navigation_level2_component(&mut transform, &cells, &map_data);
+            ),
+            (BotProgram::Level0, _) => false,
+            (BotProgram::Level1, _) => {
+                update_navigation_level1_component(&mut transform, &cells, &map_data);
+                false
+            }
+            (BotProgram::Level2 | BotProgram::Navigator, _) => {
+                update_navigation_level2_component(&mut transform, &cells, &map_data);
+                false
+            }
+        };
+        if hold_position {
+            linear_velocity.0 = Vec3::ZERO;
+            angular_velocity.0 = Vec3::ZERO;
+        } else {
             update_forward_thruster_level1_component(
                 commands.reborrow(),
                 *transform,
@@ -181,12 +352,63 @@ fn bots_movement_system(
                 &map_data,
             );
         }
+        update_logic_state_component(
+            &mut logic_state,
+            probes,
+            *transform,
+            hold_position,
+            &cells,
+            &map_data,
+        );
 
         // let move_delta = transform.forward() * BOT_MOVEMENT_SPEED * time.delta_seconds();
         // transform.translation += move_delta;
     }
 }
 
+/// Move directions forward, right, left and back of the bot, and whether the face of its cell that way is open
+fn probe_faces(transform: Transform, cells: &Cells, bounds: &IBounds3) -> [(IVec3, bool); 4] {
+    let src_cell_indices =
+        CellCoords::from_game_coordinates(transform.translation).as_cell_indices(bounds);
+    [
+        transform.forward(),
+        transform.right(),
+        transform.left(),
+        transform.back(),
+    ]
+    .map(|direction| {
+        let move_direction = calculate_move_direction_from_direction_vector(direction);
+        (
+            move_direction,
+            can_move_in_direction(src_cell_indices, move_direction, cells),
+        )
+    })
+}
+
+/// Records the probes taken before the navigation turn, and the cell the bot drives into after it
+fn update_logic_state_component(
+    logic_state: &mut BotLogicState,
+    probes: [(IVec3, bool); 4],
+    transform: Transform,
+    holding: bool,
+    cells: &Cells,
+    map_data: &MapData,
+) {
+    let src_cell_indices =
+        CellCoords::from_game_coordinates(transform.translation).as_cell_indices(&map_data.bounds);
+    let forward_move_direction =
+        calculate_move_direction_from_direction_vector(transform.forward());
+    let forward_open = can_move_in_direction(src_cell_indices, forward_move_direction, cells);
+
+    *logic_state = BotLogicState {
+        ticks: logic_state.ticks + 1,
+        probes,
+        next_cell_indices: (!holding && forward_open)
+            .then_some(src_cell_indices + forward_move_direction),
+        holding,
+    };
+}
+
 fn update_brakes_level0_component(
     transform: Transform,
     linear_velocity: &mut Mut<LinearVelocity>,
@@ -223,7 +445,6 @@ fn update_forward_thruster_level1_component(
     }
 }
 
-#[allow(dead_code)]
 fn update_navigation_level1_component(
     transform: &mut Mut<Transform>,
     cells: &Res<Cells>,
@@ -252,7 +473,6 @@ fn update_navigation_level1_component(
     }
 }
 
-#[allow(dead_code)]
 fn update_navigation_level2_component(
     transform: &mut Mut<Transform>,
     cells: &Res<Cells>,
@@ -300,6 +520,40 @@ fn update_navigation_level2_component(
     }
 }
 
+/// Turns the bot towards the next cell on the shortest path to its target.
+/// Returns true when the bot should hold its position, either on the target or because it is unreachable.
+fn update_navigation_target_component(
+    transform: &mut Mut<Transform>,
+    nav_target: &NavTarget,
+    nav_graph: &NavGraph,
+    map_data: &Res<MapData>,
+) -> bool {
+    let bounds = &map_data.bounds;
+    let src_cell_indices =
+        CellCoords::from_game_coordinates(transform.translation).as_cell_indices(bounds);
+
+    if src_cell_indices == nav_target.cell_indices {
+        return true;
+    }
+    let Some(dst_cell_indices) = nav_graph
+        .find_path(src_cell_indices, nav_target.cell_indices)
+        .and_then(|path| path.get(1).copied())
+    else {
+        return true;
+    };
+
+    let move_direction = dst_cell_indices - src_cell_indices;
+    if calculate_move_direction_from_direction_vector(transform.forward()) != move_direction {
+        // HACK: same centering as level2 to avoid collisions and off directions when turning
+        transform.translation =
+            CellCoords::from_cell_indices(src_cell_indices, bounds).as_game_coordinates();
+        let up = transform.up();
+        transform.look_to(move_direction.as_vec3(), *up);
+    }
+
+    false
+}
+
 fn can_move_in_direction_vector(
     position: Vec3,
     forward: Direction3d,
@@ -330,35 +584,7 @@ fn can_move_in_direction(src_cell_indices: IVec3, move_direction: IVec3, cells:
     debug_assert_eq!(move_direction.abs().max_element(), 1, "move_direction: {src_cell_indices:?} + {move_direction:?} currently only support moves that can be adjucent to the source cell!");
     debug_assert_eq!(move_direction.length_squared(), 1, "move_direction: {src_cell_indices:?} + {move_direction:?} currently only support moves that can be adjucent to the source cell!");
 
-    let dst_cell_indices =
-        calculate_destination_cell_indices_from_move_direction(src_cell_indices, move_direction);
-
-    let src_cell_type = cells.get_or_open_all(src_cell_indices);
-    let dst_cell_type = cells.get_or_open_all(dst_cell_indices);
-
-    let result = match move_direction {
-        IVec3::X => {
-            src_cell_type.is_open(cell::OPEN_POS_X) && dst_cell_type.is_open(cell::OPEN_NEG_X)
-        }
-        IVec3::Y => {
-            src_cell_type.is_open(cell::OPEN_POS_Y) && dst_cell_type.is_open(cell::OPEN_NEG_Y)
-        }
-        IVec3::Z => {
-            src_cell_type.is_open(cell::OPEN_POS_Z) && dst_cell_type.is_open(cell::OPEN_NEG_Z)
-        }
-        IVec3::NEG_X => {
-            src_cell_type.is_open(cell::OPEN_NEG_X) && dst_cell_type.is_open(cell::OPEN_POS_X)
-        }
-        IVec3::NEG_Y => {
-            src_cell_type.is_open(cell::OPEN_NEG_Y) && dst_cell_type.is_open(cell::OPEN_POS_Y)
-        }
-        IVec3::NEG_Z => {
-            src_cell_type.is_open(cell::OPEN_NEG_Z) && dst_cell_type.is_open(cell::OPEN_POS_Z)
-        }
-        _ => false,
-    };
-    // println!("{src_cell_indices:?} + {move_direction:?}) => {dst_cell_indices:?} => {src_cell_type:?} => {dst_cell_type:?} => {result}");
-    result
+    cells.is_face_open(src_cell_indices, move_direction)
 }
 
 fn calculate_move_direction_from_direction_vector(forward: Direction3d) -> IVec3 {
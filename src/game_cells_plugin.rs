@@ -1,8 +1,11 @@
+use core::fmt;
+
 use bevy::prelude::*;
 
 use itertools::Itertools;
 
 pub mod cell {
+    use bevy::math::IVec3;
     use derive_more::{BitAnd, BitOr};
 
     #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, BitOr, BitAnd, PartialOrd, Ord)]
@@ -54,6 +57,42 @@ pub mod cell {
         pub fn is_closed(self, direction: Self) -> bool {
             !self.is_open(direction)
         }
+
+        /// Returns a copy with the given direction opened.
+        #[must_use]
+        pub fn with_open(self, direction: Self) -> Self {
+            self | direction
+        }
+
+        /// Returns a copy with the given direction closed.
+        #[must_use]
+        pub const fn with_closed(self, direction: Self) -> Self {
+            Self(self.0 & !direction.0)
+        }
+
+        /// Openings along x and y packed in a nibble: `OPEN_NEG_X` is bit 0 up to `OPEN_POS_Y` on bit 3.
+        pub const fn xy_nibble(self) -> u8 {
+            self.0 & 0b1111
+        }
+
+        /// Inverse of `xy_nibble`, bits above the nibble are ignored.
+        pub const fn from_xy_nibble(nibble: u8) -> Self {
+            Self(nibble & 0b1111)
+        }
+    }
+
+    /// Returns the openings on the source and on the destination cell side of the face crossed when
+    /// moving one cell in `move_direction`, or `None` if it is not a move to an adjacent cell.
+    pub const fn faces_from_move_direction(move_direction: IVec3) -> Option<(Type, Type)> {
+        match move_direction {
+            IVec3::X => Some((OPEN_POS_X, OPEN_NEG_X)),
+            IVec3::Y => Some((OPEN_POS_Y, OPEN_NEG_Y)),
+            IVec3::Z => Some((OPEN_POS_Z, OPEN_NEG_Z)),
+            IVec3::NEG_X => Some((OPEN_NEG_X, OPEN_POS_X)),
+            IVec3::NEG_Y => Some((OPEN_NEG_Y, OPEN_POS_Y)),
+            IVec3::NEG_Z => Some((OPEN_NEG_Z, OPEN_POS_Z)),
+            _ => None,
+        }
     }
 }
 
@@ -63,14 +102,59 @@ pub struct Cells {
     pub size: IVec3,
 }
 
+/// Glyphs used to write cells in map strings, both sets encode only the openings along x and y
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GlyphSet {
+    /// `╔╦╗` box-drawing lines following the openings, `█` for closed cells
+    BoxDrawing,
+    /// One hex digit per cell holding `cell::Type::xy_nibble`, `0` for closed cells
+    HexNibble,
+}
+
+impl GlyphSet {
+    /// Maps made only of hex digits are hex nibble maps, anything else is read as box-drawing
+    pub fn detect(map_string: &str) -> Self {
+        let mut glyphs = map_string
+            .chars()
+            .filter(|glyph| !glyph.is_whitespace())
+            .peekable();
+        if glyphs.peek().is_some() && glyphs.all(|glyph| glyph.is_ascii_hexdigit()) {
+            Self::HexNibble
+        } else {
+            Self::BoxDrawing
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn cell_type(self, cell_char: char) -> cell::Type {
+        match self {
+            Self::BoxDrawing => cell_char_to_cell_type(cell_char),
+            Self::HexNibble => cell_char.to_digit(16).map_or(cell::EMPTY, |nibble| {
+                cell::Type::from_xy_nibble(nibble as u8)
+            }),
+        }
+    }
+
+    fn cell_char(self, cell_type: cell::Type) -> char {
+        match self {
+            Self::BoxDrawing => cell_type_to_cell_char(cell_type),
+            Self::HexNibble => {
+                char::from_digit(u32::from(cell_type.xy_nibble()), 16).unwrap_or('0')
+            }
+        }
+    }
+}
+
 impl Cells {
-    fn new(array: Vec<Vec<Vec<cell::Type>>>, size: IVec3) -> Self {
+    const fn new(array: Vec<Vec<Vec<cell::Type>>>, size: IVec3) -> Self {
         Self { array, size }
     }
 
     #[allow(clippy::cast_possible_truncation)]
     #[allow(clippy::cast_possible_wrap)]
+    /// Parses a map string in either glyph set, see `GlyphSet::detect`
     pub fn from_string(map_string: &str) -> Self {
+        let glyph_set = GlyphSet::detect(map_string);
         let cells = map_string
             .split("\n\n")
             .map(|level| {
@@ -78,7 +162,11 @@ impl Cells {
                     .lines()
                     .rev()
                     .filter(|line| !line.is_empty())
-                    .map(|line| line.chars().map(cell_char_to_cell_type).collect_vec())
+                    .map(|line| {
+                        line.chars()
+                            .map(|cell_char| glyph_set.cell_type(cell_char))
+                            .collect_vec()
+                    })
                     .collect_vec()
             })
             .collect_vec();
@@ -94,6 +182,15 @@ impl Cells {
         Self::new(cells, IVec3::new(x as i32, y as i32, z as i32))
     }
 
+    /// Creates a map of the given size with every cell closed on all sides.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn closed(size: IVec3) -> Self {
+        let size = size.max(IVec3::ZERO);
+        let array =
+            vec![vec![vec![cell::EMPTY; size.x as usize]; size.y as usize]; size.z as usize];
+        Self::new(array, size)
+    }
+
     #[allow(clippy::cast_sign_loss)]
     pub fn get(&self, indices: IVec3) -> Option<&cell::Type> {
         if indices.x >= 0
@@ -120,9 +217,119 @@ impl Cells {
         }
     }
 
+    #[allow(clippy::cast_sign_loss)]
+    pub fn get_mut(&mut self, indices: IVec3) -> Option<&mut cell::Type> {
+        if self.get(indices).is_some() {
+            self.array
+                .get_mut(indices.z as usize)?
+                .get_mut(indices.y as usize)?
+                .get_mut(indices.x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the indices of every cell in the map.
+    pub fn indices(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.array.iter().zip(0..).flat_map(|(level, z)| {
+            level.iter().zip(0..).flat_map(move |(row, y)| {
+                row.iter().zip(0..).map(move |(_, x)| IVec3::new(x, y, z))
+            })
+        })
+    }
+
     pub fn get_or_open_all(&self, coords: IVec3) -> cell::Type {
         self.get(coords).copied().unwrap_or(cell::OPEN_ALL)
     }
+
+    /// Checks if the face crossed when moving from `cell_indices` in `move_direction` is open on both sides.
+    pub fn is_face_open(&self, cell_indices: IVec3, move_direction: IVec3) -> bool {
+        cell::faces_from_move_direction(move_direction).is_some_and(|(src_face, dst_face)| {
+            self.get_or_open_all(cell_indices).is_open(src_face)
+                && self
+                    .get_or_open_all(cell_indices + move_direction)
+                    .is_open(dst_face)
+        })
+    }
+
+    /// Opens or closes the face crossed when moving from `cell_indices` in `move_direction`, keeping the
+    /// openings on both sides of the face in sync. Returns the indices of the cells that changed.
+    pub fn set_face_open(
+        &mut self,
+        cell_indices: IVec3,
+        move_direction: IVec3,
+        open: bool,
+    ) -> Vec<IVec3> {
+        let Some((src_face, dst_face)) = cell::faces_from_move_direction(move_direction) else {
+            return Vec::new();
+        };
+
+        [
+            (cell_indices, src_face),
+            (cell_indices + move_direction, dst_face),
+        ]
+        .into_iter()
+        .filter_map(|(indices, face)| {
+            let cell_type = self.get_mut(indices)?;
+            let new_cell_type = if open {
+                cell_type.with_open(face)
+            } else {
+                cell_type.with_closed(face)
+            };
+            (new_cell_type != *cell_type).then(|| {
+                *cell_type = new_cell_type;
+                indices
+            })
+        })
+        .collect_vec()
+    }
+}
+
+impl Cells {
+    /// Writes the cells in the format read by `from_string`, openings along z have no glyph and are dropped.
+    pub fn to_string_with(&self, glyph_set: GlyphSet) -> String {
+        self.array
+            .iter()
+            .map(|level| {
+                level
+                    .iter()
+                    .rev()
+                    .map(|row| {
+                        row.iter()
+                            .map(|&cell_type| glyph_set.cell_char(cell_type))
+                            .collect::<String>()
+                    })
+                    .join("\n")
+            })
+            .join("\n\n")
+    }
+}
+
+impl fmt::Display for Cells {
+    /// Writes the cells with the box-drawing glyphs, see `to_string_with`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_with(GlyphSet::BoxDrawing))
+    }
+}
+
+/// Iterates over the glyphs of a map overlay laid out exactly like the cells map string (levels separated
+/// by a blank line, rows from top to bottom), together with the indices of the cell each glyph sits on.
+pub fn map_overlay_glyphs(overlay_string: &str) -> impl Iterator<Item = (IVec3, char)> + '_ {
+    overlay_string
+        .split("\n\n")
+        .zip(0..)
+        .flat_map(|(level, z)| {
+            level
+                .lines()
+                .rev()
+                .filter(|line| !line.is_empty())
+                .zip(0..)
+                .flat_map(move |(line, y)| {
+                    line.chars()
+                        .zip(0..)
+                        .map(move |(glyph, x)| (IVec3::new(x, y, z), glyph))
+                })
+        })
 }
 
 fn map_max_or_default<T, F>(vec: &[Vec<T>], f: F) -> usize
@@ -155,6 +362,148 @@ fn cell_char_to_cell_type(cell_char: char) -> cell::Type {
     }
 }
 
+/// Inverse of `cell_char_to_cell_type`, ignoring the openings along z
+#[allow(clippy::non_ascii_literal)]
+fn cell_type_to_cell_char(cell_type: cell::Type) -> char {
+    let xy_openings = cell::Type::from_xy_nibble(cell_type.xy_nibble());
+
+    "╡╞╥╨═╗╝╚╔║╦╩╣╠╬"
+        .chars()
+        .find(|&cell_char| cell_char_to_cell_type(cell_char) == xy_openings)
+        .unwrap_or('█')
+}
+
+#[cfg(test)]
+mod test_cells_to_string {
+    use bevy_prng::WyRand;
+    use rand_core::{RngCore, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_cells_to_string_matches_map_string() {
+        #[allow(clippy::non_ascii_literal)]
+        let map_string = "
+╞═╦╗╔╦╩╡
+╞═╬╣╠╬╦╡
+██║║╠╣║█
+╞═╩╝╠╣║█
+
+╔╗╔╗╚╝║█
+╝╚╝╚╗╔╝█
+████╚╝██
+████╔╗██
+";
+
+        let cells = Cells::from_string(map_string);
+
+        assert_eq!(cells.to_string(), map_string.trim_matches('\n'));
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    #[test]
+    fn test_cells_to_string_round_trip() {
+        let mut rng = WyRand::from_seed([42; 8]);
+        let directions = [
+            cell::OPEN_NEG_X,
+            cell::OPEN_POS_X,
+            cell::OPEN_NEG_Y,
+            cell::OPEN_POS_Y,
+        ];
+
+        for _ in 0..32 {
+            let size = IVec3::new(
+                1 + (rng.next_u32() % 12) as i32,
+                1 + (rng.next_u32() % 12) as i32,
+                1 + (rng.next_u32() % 3) as i32,
+            );
+            let mut cells = Cells::closed(size);
+            for indices in cells.indices().collect_vec() {
+                let bits = rng.next_u32();
+                let cell_type = directions
+                    .into_iter()
+                    .zip(0..)
+                    .filter(|&(_, bit)| bits & (1 << bit) != 0)
+                    .fold(cell::EMPTY, |acc, (direction, _)| acc | direction);
+                if let Some(cell) = cells.get_mut(indices) {
+                    *cell = cell_type;
+                }
+            }
+
+            for glyph_set in [GlyphSet::BoxDrawing, GlyphSet::HexNibble] {
+                let round_trip = Cells::from_string(&cells.to_string_with(glyph_set));
+
+                assert_eq!(round_trip.size, cells.size);
+                assert_eq!(round_trip.array, cells.array);
+            }
+        }
+    }
+
+    #[test]
+    #[allow(clippy::non_ascii_literal)]
+    fn test_hex_nibble_glyph_set() {
+        let box_string = "╔╦╗\n╚╩╝\n\n█╞╡";
+        let hex_string = "6750\nab90\n\n0210";
+
+        assert_eq!(GlyphSet::detect(box_string), GlyphSet::BoxDrawing);
+        assert_eq!(GlyphSet::detect(hex_string), GlyphSet::HexNibble);
+        assert_eq!(GlyphSet::detect("\n\n"), GlyphSet::BoxDrawing);
+
+        let cells = Cells::from_string(hex_string);
+        assert_eq!(cells.to_string_with(GlyphSet::HexNibble), hex_string);
+        assert_eq!(cells.to_string(), "╔╦╗█\n╚╩╝█\n\n█╞╡█");
+        assert_eq!(
+            Cells::from_string(box_string).to_string_with(GlyphSet::HexNibble),
+            "675\nab9\n\n021"
+        );
+        // Upper case digits are accepted too
+        assert_eq!(
+            Cells::from_string("AB").array,
+            Cells::from_string("ab").array
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_cells_faces {
+    use super::*;
+
+    #[test]
+    fn test_set_face_open_updates_both_sides() {
+        #[allow(clippy::non_ascii_literal)]
+        let mut cells = Cells::from_string("\n╞╡\n");
+
+        assert!(cells.is_face_open(IVec3::ZERO, IVec3::X));
+
+        let changed = cells.set_face_open(IVec3::ZERO, IVec3::X, false);
+        assert_eq!(changed, [IVec3::new(0, 0, 0), IVec3::new(1, 0, 0)]);
+        assert_eq!(cells.get(IVec3::new(0, 0, 0)), Some(&cell::EMPTY));
+        assert_eq!(cells.get(IVec3::new(1, 0, 0)), Some(&cell::EMPTY));
+        assert!(!cells.is_face_open(IVec3::new(1, 0, 0), IVec3::NEG_X));
+
+        let changed = cells.set_face_open(IVec3::new(1, 0, 0), IVec3::NEG_X, true);
+        assert_eq!(changed, [IVec3::new(1, 0, 0), IVec3::new(0, 0, 0)]);
+        assert_eq!(cells.get(IVec3::new(0, 0, 0)), Some(&cell::OPEN_POS_X));
+        assert_eq!(cells.get(IVec3::new(1, 0, 0)), Some(&cell::OPEN_NEG_X));
+
+        let changed = cells.set_face_open(IVec3::ZERO, IVec3::X, true);
+        assert!(changed.is_empty());
+    }
+
+    #[test]
+    fn test_set_face_open_on_map_edge() {
+        #[allow(clippy::non_ascii_literal)]
+        let mut cells = Cells::from_string("\n╞╡\n");
+
+        let changed = cells.set_face_open(IVec3::ZERO, IVec3::NEG_X, true);
+        assert_eq!(changed, [IVec3::new(0, 0, 0)]);
+        assert_eq!(
+            cells.get(IVec3::new(0, 0, 0)),
+            Some(&(cell::OPEN_NEG_X | cell::OPEN_POS_X))
+        );
+    }
+}
+
 #[cfg(test)]
 mod test_map_load_string {
     use super::*;
@@ -177,45 +526,45 @@ mod test_map_load_string {
 
         // ╞═╦╗
         assert_eq!(
-            cells.array[0][0],
+            cells.array[0][3],
             [
                 cell::OPEN_POS_X,
                 cell::OPEN_NEG_X | cell::OPEN_POS_X,
-                cell::OPEN_NEG_X | cell::OPEN_POS_X | cell::OPEN_POS_Y,
-                cell::OPEN_NEG_X | cell::OPEN_POS_Y
+                cell::OPEN_NEG_X | cell::OPEN_POS_X | cell::OPEN_NEG_Y,
+                cell::OPEN_NEG_X | cell::OPEN_NEG_Y
             ]
         );
 
         // ╞═╬╣
         assert_eq!(
-            cells.array[0][1],
+            cells.array[0][2],
             [
                 cell::OPEN_POS_X,
                 cell::OPEN_NEG_X | cell::OPEN_POS_X,
-                cell::OPEN_NEG_X | cell::OPEN_POS_X | cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
-                cell::OPEN_NEG_X | cell::OPEN_POS_Y | cell::OPEN_NEG_Y
+                cell::OPEN_NEG_X | cell::OPEN_POS_X | cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
+                cell::OPEN_NEG_X | cell::OPEN_NEG_Y | cell::OPEN_POS_Y
             ]
         );
 
         // ██║║
         assert_eq!(
-            cells.array[0][2],
+            cells.array[0][1],
             [
                 cell::EMPTY,
                 cell::EMPTY,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y
             ]
         );
 
         // ╞═╩╝
         assert_eq!(
-            cells.array[0][3],
+            cells.array[0][0],
             [
                 cell::OPEN_POS_X,
                 cell::OPEN_NEG_X | cell::OPEN_POS_X,
-                cell::OPEN_NEG_X | cell::OPEN_POS_X | cell::OPEN_NEG_Y,
-                cell::OPEN_NEG_X | cell::OPEN_NEG_Y
+                cell::OPEN_NEG_X | cell::OPEN_POS_X | cell::OPEN_POS_Y,
+                cell::OPEN_NEG_X | cell::OPEN_POS_Y
             ]
         );
     }
@@ -243,104 +592,104 @@ mod test_map_load_string {
 
         // ╞═╦╗╔╦╩╡
         assert_eq!(
-            cells.array[0][0],
+            cells.array[0][7],
             [
                 cell::OPEN_POS_X,
                 cell::OPEN_POS_X | cell::OPEN_NEG_X,
-                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_POS_Y,
-                cell::OPEN_NEG_X | cell::OPEN_POS_Y,
-                cell::OPEN_POS_X | cell::OPEN_POS_Y,
-                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_POS_Y,
                 cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_NEG_Y,
+                cell::OPEN_NEG_X | cell::OPEN_NEG_Y,
+                cell::OPEN_POS_X | cell::OPEN_NEG_Y,
+                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_NEG_Y,
+                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_POS_Y,
                 cell::OPEN_NEG_X
             ]
         );
 
         // ╞═╬╣╠╬╦╡
         assert_eq!(
-            cells.array[0][1],
+            cells.array[0][6],
             [
                 cell::OPEN_POS_X,
                 cell::OPEN_POS_X | cell::OPEN_NEG_X,
-                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
-                cell::OPEN_NEG_X | cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
-                cell::OPEN_POS_X | cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
-                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
-                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_POS_Y,
+                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
+                cell::OPEN_NEG_X | cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
+                cell::OPEN_POS_X | cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
+                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
+                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_NEG_Y,
                 cell::OPEN_NEG_X
             ]
         );
 
         // ██║║╠╣║█
         assert_eq!(
-            cells.array[0][2],
+            cells.array[0][5],
             [
                 cell::EMPTY,
                 cell::EMPTY,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y | cell::OPEN_POS_X,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y | cell::OPEN_NEG_X,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y | cell::OPEN_POS_X,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y | cell::OPEN_NEG_X,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
                 cell::EMPTY
             ]
         );
 
         // ╞═╩╝╠╣║█
         assert_eq!(
-            cells.array[0][3],
+            cells.array[0][4],
             [
                 cell::OPEN_POS_X,
                 cell::OPEN_POS_X | cell::OPEN_NEG_X,
-                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_NEG_Y,
-                cell::OPEN_NEG_X | cell::OPEN_NEG_Y,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y | cell::OPEN_POS_X,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y | cell::OPEN_NEG_X,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
+                cell::OPEN_POS_X | cell::OPEN_NEG_X | cell::OPEN_POS_Y,
+                cell::OPEN_NEG_X | cell::OPEN_POS_Y,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y | cell::OPEN_POS_X,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y | cell::OPEN_NEG_X,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
                 cell::EMPTY
             ]
         );
 
         // ╔╗╔╗╚╝║█
         assert_eq!(
-            cells.array[0][4],
+            cells.array[0][3],
             [
-                cell::OPEN_POS_Y | cell::OPEN_POS_X,
-                cell::OPEN_POS_Y | cell::OPEN_NEG_X,
-                cell::OPEN_POS_Y | cell::OPEN_POS_X,
-                cell::OPEN_POS_Y | cell::OPEN_NEG_X,
                 cell::OPEN_NEG_Y | cell::OPEN_POS_X,
                 cell::OPEN_NEG_Y | cell::OPEN_NEG_X,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_Y,
+                cell::OPEN_NEG_Y | cell::OPEN_POS_X,
+                cell::OPEN_NEG_Y | cell::OPEN_NEG_X,
+                cell::OPEN_POS_Y | cell::OPEN_POS_X,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_X,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_Y,
                 cell::EMPTY
             ]
         );
 
         // ╝╚╝╚╗╔╝█
         assert_eq!(
-            cells.array[0][5],
+            cells.array[0][2],
             [
-                cell::OPEN_NEG_Y | cell::OPEN_NEG_X,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_X,
-                cell::OPEN_NEG_Y | cell::OPEN_NEG_X,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_X,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_X,
+                cell::OPEN_POS_Y | cell::OPEN_POS_X,
                 cell::OPEN_POS_Y | cell::OPEN_NEG_X,
                 cell::OPEN_POS_Y | cell::OPEN_POS_X,
                 cell::OPEN_NEG_Y | cell::OPEN_NEG_X,
+                cell::OPEN_NEG_Y | cell::OPEN_POS_X,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_X,
                 cell::EMPTY
             ]
         );
 
         // ████╚╝██
         assert_eq!(
-            cells.array[0][6],
+            cells.array[0][1],
             [
                 cell::EMPTY,
                 cell::EMPTY,
                 cell::EMPTY,
                 cell::EMPTY,
-                cell::OPEN_NEG_Y | cell::OPEN_POS_X,
-                cell::OPEN_NEG_Y | cell::OPEN_NEG_X,
+                cell::OPEN_POS_Y | cell::OPEN_POS_X,
+                cell::OPEN_POS_Y | cell::OPEN_NEG_X,
                 cell::EMPTY,
                 cell::EMPTY,
             ]
@@ -348,14 +697,14 @@ mod test_map_load_string {
 
         // ████╔╗██
         assert_eq!(
-            cells.array[0][7],
+            cells.array[0][0],
             [
                 cell::EMPTY,
                 cell::EMPTY,
                 cell::EMPTY,
                 cell::EMPTY,
-                cell::OPEN_POS_Y | cell::OPEN_POS_X,
-                cell::OPEN_POS_Y | cell::OPEN_NEG_X,
+                cell::OPEN_NEG_Y | cell::OPEN_POS_X,
+                cell::OPEN_NEG_Y | cell::OPEN_NEG_X,
                 cell::EMPTY,
                 cell::EMPTY,
             ]
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use itertools::Itertools;
+
+use crate::game_bots_plugin::Bot;
+use crate::game_cells_plugin::Cells;
+use crate::game_coordinates_utils::CellCoords;
+use crate::game_scene_plugin::{CellsChangedEvent, CellsSpawnedEvent};
+use crate::game_setup_data::MapData;
+
+#[derive(Debug)]
+pub struct DoorsPlugin;
+
+impl Plugin for DoorsPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app.add_systems(
+            Update,
+            (
+                spawn_doors_system,
+                (door_timers_system, door_switches_system).before(apply_doors_system),
+                apply_doors_system,
+            ),
+        );
+    }
+}
+
+/// A face between two cells that can be opened and closed at runtime
+#[derive(Component, Clone, Debug)]
+pub struct Door {
+    pub cell_indices: IVec3,
+    pub move_direction: IVec3,
+    pub trigger: DoorTrigger,
+    /// Whether the door should be open, applied to `Cells` on the next update
+    pub open: bool,
+}
+
+impl Door {
+    pub const fn new(cell_indices: IVec3, move_direction: IVec3, trigger: DoorTrigger) -> Self {
+        Self {
+            cell_indices,
+            move_direction,
+            trigger,
+            open: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum DoorTrigger {
+    /// Toggles the door every time the timer finishes
+    Timer(Timer),
+    /// Keeps the door open while a bot stands on the switch cell
+    Switch { switch_cell_indices: IVec3 },
+}
+
+/// Doors placed on the map, spawned once the cells are spawned
+#[derive(Resource, Debug, Default)]
+pub struct DoorPlacements {
+    pub doors: Vec<Door>,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn spawn_doors_system(
+    mut commands: Commands,
+    mut cells_spawned_reader: EventReader<CellsSpawnedEvent>,
+    door_placements: Option<Res<DoorPlacements>>,
+    cells: Option<Res<Cells>>,
+) {
+    if let Some(door_placements) = door_placements {
+        if let Some(cells) = cells {
+            for CellsSpawnedEvent {} in cells_spawned_reader.read() {
+                for door in &door_placements.doors {
+                    // Doors start in whatever state the map was drawn with
+                    let open = cells.is_face_open(door.cell_indices, door.move_direction);
+                    _ = commands.spawn(Door {
+                        open,
+                        ..door.clone()
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn door_timers_system(time: Res<Time>, mut query: Query<&mut Door>) {
+    for mut door in &mut query {
+        if let DoorTrigger::Timer(timer) = &mut door.trigger {
+            if timer.tick(time.delta()).just_finished() {
+                door.open = !door.open;
+            }
+        }
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn door_switches_system(
+    map_data: Option<Res<MapData>>,
+    bots_query: Query<&Transform, With<Bot>>,
+    mut query: Query<&mut Door>,
+) {
+    let Some(map_data) = map_data else {
+        return;
+    };
+
+    let occupied_cells = bots_query
+        .iter()
+        .map(|transform| {
+            CellCoords::from_game_coordinates(transform.translation)
+                .as_cell_indices(&map_data.bounds)
+        })
+        .collect_vec();
+
+    for mut door in &mut query {
+        if let DoorTrigger::Switch {
+            switch_cell_indices,
+        } = door.trigger
+        {
+            let open = occupied_cells.contains(&switch_cell_indices);
+            if door.open != open {
+                door.open = open;
+            }
+        }
+    }
+}
+
+/// Writes the doors state into `Cells` and notifies everyone about the changed cells
+#[allow(clippy::needless_pass_by_value)]
+fn apply_doors_system(
+    cells: Option<ResMut<Cells>>,
+    mut cells_changed_writer: EventWriter<CellsChangedEvent>,
+    query: Query<&Door, Changed<Door>>,
+) {
+    let Some(mut cells) = cells else {
+        return;
+    };
+
+    let cell_indices = query
+        .iter()
+        .flat_map(|door| cells.set_face_open(door.cell_indices, door.move_direction, door.open))
+        .unique()
+        .collect_vec();
+
+    if !cell_indices.is_empty() {
+        _ = cells_changed_writer.send(CellsChangedEvent { cell_indices });
+    }
+}
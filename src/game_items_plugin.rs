@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_xpbd_3d::prelude::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::game_bots_plugin::Bot;
+use crate::game_cells_plugin::map_overlay_glyphs;
+use crate::game_coordinates_utils::CellCoords;
+use crate::game_physics_layers::Layer;
+use crate::game_scene_plugin::CellsSpawnedEvent;
+use crate::game_setup_data::MapData;
+
+const ITEM_PICKUP_RADIUS: f32 = 0.15;
+
+#[derive(Debug)]
+pub struct ItemsPlugin;
+
+impl Plugin for ItemsPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app
+            .add_event::<ItemSpawnedEvent>()
+            .add_event::<ItemPickedUpEvent>()
+            .add_systems(Update, (spawn_items_system, items_pickup_system));
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Item {
+    DataChip,
+    Key,
+    Battery,
+}
+
+impl Item {
+    pub const ALL: [Self; 3] = [Self::DataChip, Self::Key, Self::Battery];
+
+    /// Parses an item placement glyph from the map items overlay
+    pub const fn from_char(item_char: char) -> Option<Self> {
+        match item_char {
+            'd' => Some(Self::DataChip),
+            'k' => Some(Self::Key),
+            'b' => Some(Self::Battery),
+            _ => None,
+        }
+    }
+
+    /// Points scored by the team of the bot picking up the item
+    pub const fn score(self) -> u32 {
+        match self {
+            Self::DataChip => 3,
+            Self::Key | Self::Battery => 1,
+        }
+    }
+}
+
+/// Items carried by a bot
+#[derive(Component, Debug, Default)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+}
+
+/// Item placements on the map, indexed by cell indices
+#[derive(Resource, Debug, Default)]
+pub struct ItemPlacements {
+    pub items: Vec<(IVec3, Item)>,
+}
+
+impl ItemPlacements {
+    /// Parses an items overlay laid out exactly like the cells map string, where every glyph that is not
+    /// an item leaves the cell empty.
+    pub fn from_string(overlay_string: &str) -> Self {
+        let items = map_overlay_glyphs(overlay_string)
+            .filter_map(|(cell_indices, glyph)| {
+                Item::from_char(glyph).map(|item| (cell_indices, item))
+            })
+            .collect_vec();
+
+        Self { items }
+    }
+}
+
+#[derive(Event, Debug)]
+// Define an event to represent the spawning of an item
+pub struct ItemSpawnedEvent {
+    pub entity: Entity,
+    pub item: Item,
+}
+
+#[derive(Event, Debug)]
+// Define an event to represent a bot picking up an item
+pub struct ItemPickedUpEvent {
+    pub bot: Entity,
+    pub item: Item,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn spawn_items_system(
+    mut commands: Commands,
+    mut cells_spawned_reader: EventReader<CellsSpawnedEvent>,
+    mut item_spawned_writer: EventWriter<ItemSpawnedEvent>,
+    item_placements: Option<Res<ItemPlacements>>,
+    map_data: Option<Res<MapData>>,
+) {
+    if let Some(item_placements) = item_placements {
+        if let Some(map_data) = map_data {
+            for CellsSpawnedEvent {} in cells_spawned_reader.read() {
+                for &(cell_indices, item) in &item_placements.items {
+                    let transform = CellCoords::from_cell_indices(cell_indices, &map_data.bounds)
+                        .as_game_coordinates_transform();
+                    let entity = spawn_item_with_transform(&mut commands, item, transform);
+
+                    _ = item_spawned_writer.send(ItemSpawnedEvent { entity, item });
+                }
+            }
+        }
+    }
+}
+
+fn spawn_item_with_transform(commands: &mut Commands, item: Item, transform: Transform) -> Entity {
+    commands
+        .spawn((
+            item,
+            SpatialBundle::from_transform(transform),
+            RigidBody::Static,
+            Collider::sphere(ITEM_PICKUP_RADIUS),
+            Sensor,
+            CollisionLayers::new([Layer::Items], [Layer::Bots]), // Items are only picked up by bots
+        ))
+        .id()
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn items_pickup_system(
+    mut commands: Commands,
+    mut collision_started_reader: EventReader<CollisionStarted>,
+    mut item_picked_up_writer: EventWriter<ItemPickedUpEvent>,
+    items_query: Query<&Item>,
+    mut bots_query: Query<&mut Inventory, With<Bot>>,
+) {
+    let mut picked_up = HashSet::new();
+
+    for &CollisionStarted(entity1, entity2) in collision_started_reader.read() {
+        let (item_entity, bot_entity) = if items_query.contains(entity1) {
+            (entity1, entity2)
+        } else {
+            (entity2, entity1)
+        };
+
+        let Ok(&item) = items_query.get(item_entity) else {
+            continue;
+        };
+        let Ok(mut inventory) = bots_query.get_mut(bot_entity) else {
+            continue;
+        };
+        // The same item can touch several bots within one physics step, first one wins
+        if !picked_up.insert(item_entity) {
+            continue;
+        }
+
+        inventory.items.push(item);
+        commands.entity(item_entity).despawn_recursive();
+
+        _ = item_picked_up_writer.send(ItemPickedUpEvent {
+            bot: bot_entity,
+            item,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test_item_placements_load_string {
+    use super::*;
+
+    #[test]
+    fn test_item_placements_load_string_1x4x4() {
+        let overlay_string = "
+d...
+..k.
+....
+b..d
+";
+
+        let item_placements = ItemPlacements::from_string(overlay_string);
+
+        assert_eq!(
+            item_placements.items,
+            [
+                (IVec3::new(0, 0, 0), Item::Battery),
+                (IVec3::new(3, 0, 0), Item::DataChip),
+                (IVec3::new(2, 2, 0), Item::Key),
+                (IVec3::new(0, 3, 0), Item::DataChip),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_item_placements_load_string_2_levels() {
+        let overlay_string = "
+.k
+..
+
+..
+b.
+";
+
+        let item_placements = ItemPlacements::from_string(overlay_string);
+
+        assert_eq!(
+            item_placements.items,
+            [
+                (IVec3::new(1, 1, 0), Item::Key),
+                (IVec3::new(0, 0, 1), Item::Battery),
+            ]
+        );
+    }
+}
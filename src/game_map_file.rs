@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+
+use crate::game_cells_plugin::{Cells, GlyphSet};
+use crate::game_doors_plugin::{Door, DoorPlacements, DoorTrigger};
+use crate::game_items_plugin::{Item, ItemPlacements};
+use crate::game_raid_plugin::CaptureCells;
+use crate::game_scene_plugin::{MapSource, MAP_OVERLAY_SEPARATOR};
+use crate::game_setup_data::MapData;
+use crate::game_teams_plugin::{SpawnPoints, Team};
+
+/// Current version of the RON map file format, plain glyph grid map files count as version 0
+pub const MAP_FILE_VERSION: u32 = 1;
+
+/// A RON map file: the glyph grid plus everything placed on it
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MapFile {
+    pub version: u32,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    /// Game coordinates the center of the map is placed at
+    #[serde(default)]
+    pub origin: IVec3,
+    /// Glyph grid in the same layout as the plain map files
+    pub cells: String,
+    #[serde(default)]
+    pub spawn_points: Vec<(IVec3, Team)>,
+    #[serde(default)]
+    pub items: Vec<(IVec3, Item)>,
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+    #[serde(default)]
+    pub doors: Vec<MapDoor>,
+    /// Expected seconds to complete the map, per game mode
+    #[serde(default)]
+    pub par_times: BTreeMap<String, f32>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Objective {
+    /// Cell the attackers have to capture in a raid
+    Capture(IVec3),
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct MapDoor {
+    pub cell: IVec3,
+    pub direction: IVec3,
+    pub trigger: MapDoorTrigger,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum MapDoorTrigger {
+    /// Toggles every given number of seconds
+    Timer(f32),
+    /// Open while a bot stands on the given cell
+    Switch(IVec3),
+}
+
+/// Metadata of the spawned map
+#[derive(Resource, Clone, Debug, Default)]
+pub struct MapInfo {
+    pub name: String,
+    pub author: String,
+    pub par_times: BTreeMap<String, f32>,
+}
+
+impl MapFile {
+    /// Captures the spawned map, doors are saved in their current state
+    pub fn from_spawned_map(
+        cells: &Cells,
+        map_data: &MapData,
+        map_info: &MapInfo,
+        spawn_points: &SpawnPoints,
+        item_placements: &ItemPlacements,
+        capture_cells: &CaptureCells,
+        door_placements: &DoorPlacements,
+    ) -> Self {
+        Self {
+            version: MAP_FILE_VERSION,
+            name: map_info.name.clone(),
+            author: map_info.author.clone(),
+            origin: map_data.bounds.min + map_data.bounds.size() / 2,
+            cells: cells.to_string(),
+            spawn_points: spawn_points.spawn_points.clone(),
+            items: item_placements.items.clone(),
+            objectives: capture_cells
+                .cell_indices
+                .iter()
+                .map(|&cell_indices| Objective::Capture(cell_indices))
+                .collect(),
+            doors: door_placements
+                .doors
+                .iter()
+                .map(|door| MapDoor {
+                    cell: door.cell_indices,
+                    direction: door.move_direction,
+                    trigger: match &door.trigger {
+                        DoorTrigger::Timer(timer) => {
+                            MapDoorTrigger::Timer(timer.duration().as_secs_f32())
+                        }
+                        DoorTrigger::Switch {
+                            switch_cell_indices,
+                        } => MapDoorTrigger::Switch(*switch_cell_indices),
+                    },
+                })
+                .collect(),
+            par_times: map_info.par_times.clone(),
+        }
+    }
+
+    /// Writes the map with the glyph grid as a literal block, the way map files are written by hand
+    pub fn to_ron_string(&self) -> Result<String, String> {
+        let without_cells = Self {
+            cells: String::new(),
+            ..self.clone()
+        };
+        let ron_string = to_string_pretty(&without_cells, PrettyConfig::default())
+            .map_err(|error| error.to_string())?;
+
+        let cells_literal = format!("cells: \"\n{}\n\"", self.cells.trim_matches('\n'));
+        Ok(ron_string.replacen("cells: \"\"", &cells_literal, 1))
+    }
+
+    /// Rejects values the simulation can not run with, e.g. negative door timers
+    pub fn validate(&self) -> Result<(), String> {
+        for door in &self.doors {
+            if let MapDoorTrigger::Timer(seconds) = door.trigger {
+                if Duration::try_from_secs_f32(seconds).is_err() {
+                    return Err(format!(
+                        "door at {} has an invalid timer of {seconds} seconds",
+                        door.cell
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Expects a validated map file
+    pub fn into_map_source(self) -> MapSource {
+        MapSource {
+            cells_string: self.cells.trim_matches('\n').to_owned(),
+            overlay_string: String::new(),
+            doors: self
+                .doors
+                .into_iter()
+                .map(|door| {
+                    let trigger = match door.trigger {
+                        MapDoorTrigger::Timer(seconds) => {
+                            DoorTrigger::Timer(Timer::from_seconds(seconds, TimerMode::Repeating))
+                        }
+                        MapDoorTrigger::Switch(switch_cell_indices) => DoorTrigger::Switch {
+                            switch_cell_indices,
+                        },
+                    };
+                    Door::new(door.cell, door.direction, trigger)
+                })
+                .collect(),
+            maze: None,
+            info: MapInfo {
+                name: self.name,
+                author: self.author,
+                par_times: self.par_times,
+            },
+            origin: self.origin,
+            spawn_points: self.spawn_points,
+            items: self.items,
+            capture_cells: self
+                .objectives
+                .into_iter()
+                .map(|Objective::Capture(cell_indices)| cell_indices)
+                .collect(),
+        }
+    }
+}
+
+/// Parses either a RON map file or a plain glyph grid map file
+pub fn parse_map_file(map_string: &str) -> Result<MapSource, String> {
+    if !map_string.trim_start().starts_with('(') {
+        return Ok(MapSource::from_map_string(map_string));
+    }
+
+    let map_file: MapFile = ron::from_str(map_string).map_err(|error| error.to_string())?;
+    if map_file.version > MAP_FILE_VERSION {
+        return Err(format!(
+            "map file version {} is newer than the supported version {MAP_FILE_VERSION}",
+            map_file.version
+        ));
+    }
+    map_file.validate()?;
+    Ok(map_file.into_map_source())
+}
+
+/// Rewrites the glyph grid of a RON or plain map file with the given glyph set, leaving everything else as is
+pub fn convert_map_file(map_string: &str, glyph_set: GlyphSet) -> Result<String, String> {
+    let convert_cells = |cells_string: &str| {
+        Cells::from_string(cells_string.trim_matches('\n')).to_string_with(glyph_set)
+    };
+
+    if !map_string.trim_start().starts_with('(') {
+        let map_source = MapSource::from_map_string(map_string);
+        let cells_string = convert_cells(&map_source.cells_string);
+        return Ok(if map_source.overlay_string.is_empty() {
+            format!("{cells_string}\n")
+        } else {
+            format!(
+                "{cells_string}\n{MAP_OVERLAY_SEPARATOR}\n{}\n",
+                map_source.overlay_string
+            )
+        });
+    }
+
+    let mut map_file: MapFile = ron::from_str(map_string).map_err(|error| error.to_string())?;
+    let cells_string = format!("\n{}\n", convert_cells(&map_file.cells));
+
+    // Hand written maps keep their layout and comments when the grid is written as a literal block
+    let cells_literal = format!("\"{}\"", map_file.cells);
+    if map_string.contains(&cells_literal) {
+        return Ok(map_string.replacen(&cells_literal, &format!("\"{cells_string}\""), 1));
+    }
+
+    map_file.cells = cells_string;
+    map_file.to_ron_string()
+}
+
+pub fn load_map_file(path: &str) -> Result<MapSource, String> {
+    let map_string =
+        fs::read_to_string(path).map_err(|error| format!("failed to read map {path}: {error}"))?;
+    parse_map_file(&map_string).map_err(|error| format!("failed to parse map {path}: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_map_file() {
+        #[allow(clippy::non_ascii_literal)]
+        let map_string = r#"
+(
+    version: 1,
+    name: "Corridor",
+    origin: (2, 0, 0),
+    cells: "
+╞═╗
+╞═╝
+",
+    spawn_points: [((0, 0, 0), Red), ((0, 1, 0), Blue)],
+    items: [((1, 1, 0), DataChip)],
+    objectives: [Capture((2, 1, 0))],
+    par_times: {"raid": 12.5},
+)
+"#;
+
+        let map_source = parse_map_file(map_string).unwrap_or_else(|error| panic!("{error}"));
+
+        assert_eq!(map_source.info.name, "Corridor");
+        assert!(map_source.info.author.is_empty());
+        assert_eq!(map_source.info.par_times.get("raid"), Some(&12.5));
+        assert_eq!(map_source.origin, IVec3::new(2, 0, 0));
+        assert_eq!(map_source.cells_string.lines().count(), 2);
+        assert_eq!(
+            map_source.spawn_points,
+            [(IVec3::ZERO, Team::Red), (IVec3::Y, Team::Blue)]
+        );
+        assert_eq!(map_source.items, [(IVec3::new(1, 1, 0), Item::DataChip)]);
+        assert_eq!(map_source.capture_cells, [IVec3::new(2, 1, 0)]);
+    }
+
+    #[test]
+    fn test_parse_map_file_rejects_invalid_door_timers() {
+        for seconds in ["-3.0", "NaN", "inf"] {
+            let map_string = format!(
+                "(version: 1, cells: \"\", doors: [(cell: (0, 0, 0), direction: (1, 0, 0), trigger: Timer({seconds}))])"
+            );
+            assert!(parse_map_file(&map_string).is_err(), "{seconds}");
+        }
+
+        let map_string =
+            "(version: 1, cells: \"\", doors: [(cell: (0, 0, 0), direction: (1, 0, 0), trigger: Timer(3.0))])";
+        assert!(parse_map_file(map_string).is_ok());
+    }
+
+    #[test]
+    fn test_parse_map_file_versions() {
+        assert!(parse_map_file("(version: 2, cells: \"\")").is_err());
+        assert!(parse_map_file("(cells: \"\")").is_err());
+
+        // Plain glyph grids are version 0 and still load
+        let map_source =
+            parse_map_file("\n.R\n---\nB.\n").unwrap_or_else(|error| panic!("{error}"));
+        assert_eq!(map_source.overlay_string, "B.");
+    }
+
+    #[test]
+    #[allow(clippy::non_ascii_literal)]
+    fn test_convert_map_file() {
+        let plain_map = "╞═╗\n╞═╝\n---\nR..\n";
+        let hex_map = convert_map_file(plain_map, GlyphSet::HexNibble)
+            .unwrap_or_else(|error| panic!("{error}"));
+        assert_eq!(hex_map, "235\n239\n---\nR..\n");
+        assert_eq!(
+            convert_map_file(&hex_map, GlyphSet::BoxDrawing).as_deref(),
+            Ok(plain_map)
+        );
+
+        let ron_map = include_str!("../assets/maps/spiral.ron");
+        let hex_ron_map = convert_map_file(ron_map, GlyphSet::HexNibble)
+            .unwrap_or_else(|error| panic!("{error}"));
+        assert!(hex_ron_map.is_ascii());
+        assert_eq!(
+            parse_map_file(&hex_ron_map).map(|map_source| map_source.spawn_points),
+            parse_map_file(ron_map).map(|map_source| map_source.spawn_points)
+        );
+        assert_eq!(
+            convert_map_file(&hex_ron_map, GlyphSet::BoxDrawing).as_deref(),
+            Ok(ron_map)
+        );
+    }
+
+    #[test]
+    fn test_map_file_to_ron_string() {
+        let map_file = ron::from_str::<MapFile>(include_str!("../assets/maps/spiral.ron"))
+            .unwrap_or_else(|error| panic!("{error}"));
+
+        let ron_string = map_file
+            .to_ron_string()
+            .unwrap_or_else(|error| panic!("{error}"));
+        let round_trip =
+            ron::from_str::<MapFile>(&ron_string).unwrap_or_else(|error| panic!("{error}"));
+
+        assert!(ron_string.contains(&map_file.cells));
+        assert_eq!(
+            round_trip.cells.trim_matches('\n'),
+            map_file.cells.trim_matches('\n')
+        );
+        assert_eq!(round_trip.spawn_points, map_file.spawn_points);
+        assert_eq!(round_trip.doors, map_file.doors);
+        assert_eq!(round_trip.par_times, map_file.par_times);
+    }
+
+    #[test]
+    fn test_bundled_map_matches_default_map() {
+        let map_source = parse_map_file(include_str!("../assets/maps/spiral.ron"))
+            .unwrap_or_else(|error| panic!("{error}"));
+        let default_map_source = MapSource::default();
+
+        assert_eq!(
+            map_source.cells_string,
+            default_map_source.cells_string.trim_matches('\n')
+        );
+        assert_eq!(map_source.spawn_points.len(), 4);
+        assert_eq!(map_source.items.len(), 5);
+        assert_eq!(map_source.capture_cells.len(), 1);
+        assert_eq!(map_source.doors.len(), default_map_source.doors.len());
+    }
+}
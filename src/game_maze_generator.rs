@@ -0,0 +1,564 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use itertools::Itertools;
+use rand_core::RngCore;
+
+use crate::game_cells_plugin::{cell, Cells};
+
+/// Generated mazes are a single level, bots can not move between levels
+const MAZE_DIRECTIONS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y];
+
+const DEFAULT_MAZE_SIZE: IVec2 = IVec2::new(16, 8);
+
+const ROOM_ATTEMPTS: usize = 32;
+const ROOM_MIN_SIZE: i32 = 2;
+const ROOM_MAX_SIZE: i32 = 4;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MazeAlgorithm {
+    /// Long winding corridors with few branches
+    RecursiveBacktracker,
+    /// Many short dead ends branching out of a central area
+    Prim,
+    /// Many short dead ends spread evenly over the map
+    Kruskal,
+    /// Unbiased, every perfect maze is equally likely
+    Wilson,
+    /// Rectangular rooms joined by corridors, the cells in between stay closed
+    Rooms,
+}
+
+impl MazeAlgorithm {
+    pub const ALL: [Self; 5] = [
+        Self::RecursiveBacktracker,
+        Self::Prim,
+        Self::Kruskal,
+        Self::Wilson,
+        Self::Rooms,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::RecursiveBacktracker => "backtracker",
+            Self::Prim => "prim",
+            Self::Kruskal => "kruskal",
+            Self::Wilson => "wilson",
+            Self::Rooms => "rooms",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MazeSettings {
+    pub algorithm: MazeAlgorithm,
+    pub size: IVec2,
+    /// Fraction of the walls left between walkable cells that get knocked down after generation,
+    /// 0 keeps perfect mazes free of loops
+    pub loop_density: f32,
+}
+
+impl MazeSettings {
+    pub const fn new(algorithm: MazeAlgorithm) -> Self {
+        Self {
+            algorithm,
+            size: DEFAULT_MAZE_SIZE,
+            loop_density: 0.,
+        }
+    }
+
+    /// Parses `<algorithm>[:<width>x<height>[:<loop density>]]`, e.g. `wilson:24x12:0.1`
+    pub fn from_spec(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(':');
+        let algorithm_name = parts.next().unwrap_or_default();
+        let algorithm = MazeAlgorithm::from_name(algorithm_name)
+            .ok_or_else(|| format!("unknown maze algorithm: {algorithm_name}"))?;
+        let mut settings = Self::new(algorithm);
+
+        if let Some(size) = parts.next() {
+            let (width, height) = size
+                .split_once('x')
+                .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                .filter(|&(width, height)| width > 0 && height > 0)
+                .ok_or_else(|| format!("invalid maze size: {size}"))?;
+            settings.size = IVec2::new(width, height);
+        }
+        if let Some(loop_density) = parts.next() {
+            settings.loop_density = loop_density
+                .parse()
+                .map_err(|_| format!("invalid maze loop density: {loop_density}"))?;
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Generates a single level maze with the openings on both sides of every face in sync
+pub fn generate_maze(settings: &MazeSettings, rng: &mut impl RngCore) -> Cells {
+    let mut cells = Cells::closed(settings.size.extend(1));
+
+    match settings.algorithm {
+        MazeAlgorithm::RecursiveBacktracker => carve_recursive_backtracker(&mut cells, rng),
+        MazeAlgorithm::Prim => carve_prim(&mut cells, rng),
+        MazeAlgorithm::Kruskal => carve_kruskal(&mut cells, rng),
+        MazeAlgorithm::Wilson => carve_wilson(&mut cells, rng),
+        MazeAlgorithm::Rooms => carve_rooms(&mut cells, rng),
+    }
+    carve_loops(&mut cells, settings.loop_density, rng);
+
+    cells
+}
+
+/// Builds a raid map overlay for a generated maze: red spawn points in the bottom left corner and
+/// the capture cell in the top right corner, next to the blue spawn points
+pub fn raid_overlay(cells: &Cells) -> String {
+    let walkable_cells = cells
+        .indices()
+        .filter(|&indices| is_walkable(cells, indices))
+        .collect_vec();
+    let nearest_to = |target: IVec3| {
+        walkable_cells
+            .iter()
+            .copied()
+            .sorted_by_key(move |&indices| {
+                let distance = (indices - target).abs();
+                distance.x + distance.y + distance.z
+            })
+    };
+
+    let capture_cell = nearest_to(cells.size - IVec3::ONE).next();
+    let red_cells = nearest_to(IVec3::ZERO)
+        .filter(|&indices| Some(indices) != capture_cell)
+        .take(2)
+        .collect_vec();
+    let blue_cells = capture_cell
+        .map(|capture_cell| {
+            nearest_to(capture_cell)
+                .filter(|&indices| indices != capture_cell && !red_cells.contains(&indices))
+                .take(2)
+                .collect_vec()
+        })
+        .unwrap_or_default();
+
+    let glyphs = capture_cell
+        .map(|indices| (indices, 'C'))
+        .into_iter()
+        .chain(red_cells.into_iter().map(|indices| (indices, 'R')))
+        .chain(blue_cells.into_iter().map(|indices| (indices, 'B')))
+        .collect::<HashMap<_, _>>();
+
+    (0..cells.size.y)
+        .rev()
+        .map(|y| {
+            (0..cells.size.x)
+                .map(|x| glyphs.get(&IVec3::new(x, y, 0)).copied().unwrap_or('.'))
+                .collect::<String>()
+        })
+        .join("\n")
+}
+
+fn carve_recursive_backtracker(cells: &mut Cells, rng: &mut impl RngCore) {
+    let Some(start) = random_cell(cells, rng) else {
+        return;
+    };
+    let mut visited = HashSet::from([start]);
+    let mut stack = vec![start];
+
+    while let Some(&current) = stack.last() {
+        let unvisited = neighbours(cells, current)
+            .filter(|neighbour| !visited.contains(neighbour))
+            .collect_vec();
+        if let Some(&next) = choose(&unvisited, rng) {
+            carve(cells, current, next);
+            _ = visited.insert(next);
+            stack.push(next);
+        } else {
+            _ = stack.pop();
+        }
+    }
+}
+
+fn carve_prim(cells: &mut Cells, rng: &mut impl RngCore) {
+    let Some(start) = random_cell(cells, rng) else {
+        return;
+    };
+    let mut visited = HashSet::from([start]);
+    let mut frontier = neighbours(cells, start)
+        .map(|neighbour| (start, neighbour))
+        .collect_vec();
+
+    while !frontier.is_empty() {
+        let (from, to) = frontier.swap_remove(random_index(frontier.len(), rng));
+        if visited.insert(to) {
+            carve(cells, from, to);
+            frontier.extend(
+                neighbours(cells, to)
+                    .filter(|neighbour| !visited.contains(neighbour))
+                    .map(|neighbour| (to, neighbour)),
+            );
+        }
+    }
+}
+
+fn carve_kruskal(cells: &mut Cells, rng: &mut impl RngCore) {
+    let mut edges = cells
+        .indices()
+        .flat_map(|indices| [(indices, indices + IVec3::X), (indices, indices + IVec3::Y)])
+        .filter(|&(_, neighbour)| cells.get(neighbour).is_some())
+        .collect_vec();
+    shuffle(&mut edges, rng);
+
+    // Disjoint sets over the cells, every set is a connected region of the maze
+    let cell_ids: HashMap<IVec3, usize> = cells.indices().zip(0..).collect();
+    let mut regions = DisjointSets::new(cell_ids.len());
+
+    for (a, b) in edges {
+        let (Some(&id_a), Some(&id_b)) = (cell_ids.get(&a), cell_ids.get(&b)) else {
+            continue;
+        };
+        if regions.union(id_a, id_b) {
+            carve(cells, a, b);
+        }
+    }
+}
+
+/// Union-find with path halving and union by rank, near constant time per operation on any maze size
+#[derive(Debug)]
+struct DisjointSets {
+    parents: Vec<usize>,
+    ranks: Vec<u8>,
+}
+
+// Set ids are always below the number of sets, indexing can not go out of bounds
+#[allow(clippy::indexing_slicing)]
+impl DisjointSets {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            ranks: vec![0; len],
+        }
+    }
+
+    fn find(&mut self, mut id: usize) -> usize {
+        while self.parents[id] != id {
+            self.parents[id] = self.parents[self.parents[id]];
+            id = self.parents[id];
+        }
+        id
+    }
+
+    /// Merges the sets of `a` and `b`, returns false if they already were the same set
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (root_a, root_b) = (self.find(a), self.find(b));
+        if root_a == root_b {
+            return false;
+        }
+
+        let (low, high) = if self.ranks[root_a] < self.ranks[root_b] {
+            (root_a, root_b)
+        } else {
+            (root_b, root_a)
+        };
+        self.parents[low] = high;
+        if self.ranks[low] == self.ranks[high] {
+            self.ranks[high] += 1;
+        }
+        true
+    }
+}
+
+fn carve_wilson(cells: &mut Cells, rng: &mut impl RngCore) {
+    let Some(start) = random_cell(cells, rng) else {
+        return;
+    };
+    let mut in_maze = HashSet::from([start]);
+    let mut walk_starts = cells.indices().collect_vec();
+    shuffle(&mut walk_starts, rng);
+
+    for walk_start in walk_starts {
+        // Random walk until the maze is hit, revisiting a cell overwrites its step which erases the loop
+        let mut steps = HashMap::new();
+        let mut current = walk_start;
+        while !in_maze.contains(&current) {
+            let candidates = neighbours(cells, current).collect_vec();
+            let Some(&next) = choose(&candidates, rng) else {
+                break;
+            };
+            _ = steps.insert(current, next);
+            current = next;
+        }
+
+        let mut current = walk_start;
+        while let Some(&next) = steps.get(&current).filter(|_| !in_maze.contains(&current)) {
+            carve(cells, current, next);
+            _ = in_maze.insert(current);
+            current = next;
+        }
+    }
+}
+
+fn carve_rooms(cells: &mut Cells, rng: &mut impl RngCore) {
+    let size = cells.size;
+    let mut rooms: Vec<(IVec3, IVec3)> = vec![];
+
+    for _ in 0..ROOM_ATTEMPTS {
+        let room_size = IVec3::new(
+            random_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE, rng),
+            random_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE, rng),
+            1,
+        )
+        .min(size);
+        let min = IVec3::new(
+            random_range(0, size.x - room_size.x, rng),
+            random_range(0, size.y - room_size.y, rng),
+            0,
+        );
+        let max = min + room_size - IVec3::ONE;
+
+        // Keep at least one closed cell between rooms so they stay distinct
+        let overlaps = rooms.iter().any(|&(other_min, other_max)| {
+            (min - IVec3::ONE).cmple(other_max).all() && (max + IVec3::ONE).cmpge(other_min).all()
+        });
+        if overlaps {
+            continue;
+        }
+
+        for indices in cells.indices().collect_vec() {
+            if indices.cmpge(min).all() && indices.cmple(max).all() {
+                for direction in [IVec3::X, IVec3::Y] {
+                    if (indices + direction).cmple(max).all() {
+                        carve(cells, indices, indices + direction);
+                    }
+                }
+            }
+        }
+        rooms.push((min, max));
+    }
+
+    // Join every room to the previous one with an L shaped corridor between their centers
+    for ((prev_min, prev_max), (min, max)) in rooms.iter().copied().tuple_windows() {
+        let mut current = (prev_min + prev_max) / 2;
+        let target = (min + max) / 2;
+        while current != target {
+            let step = if current.x == target.x {
+                IVec3::new(0, (target.y - current.y).signum(), 0)
+            } else {
+                IVec3::new((target.x - current.x).signum(), 0, 0)
+            };
+            carve(cells, current, current + step);
+            current += step;
+        }
+    }
+}
+
+fn carve_loops(cells: &mut Cells, loop_density: f32, rng: &mut impl RngCore) {
+    let mut closed_faces = cells
+        .indices()
+        .flat_map(|indices| [(indices, IVec3::X), (indices, IVec3::Y)])
+        .filter(|&(indices, direction)| {
+            is_walkable(cells, indices)
+                && is_walkable(cells, indices + direction)
+                && !cells.is_face_open(indices, direction)
+        })
+        .collect_vec();
+    shuffle(&mut closed_faces, rng);
+
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    let count = (loop_density.clamp(0., 1.) * closed_faces.len() as f32).round() as usize;
+    for (indices, direction) in closed_faces.into_iter().take(count) {
+        _ = cells.set_face_open(indices, direction, true);
+    }
+}
+
+fn carve(cells: &mut Cells, from: IVec3, to: IVec3) {
+    _ = cells.set_face_open(from, to - from, true);
+}
+
+fn is_walkable(cells: &Cells, indices: IVec3) -> bool {
+    cells
+        .get(indices)
+        .is_some_and(|&cell_type| cell_type != cell::EMPTY)
+}
+
+fn neighbours(cells: &Cells, indices: IVec3) -> impl Iterator<Item = IVec3> + '_ {
+    MAZE_DIRECTIONS
+        .into_iter()
+        .map(move |direction| indices + direction)
+        .filter(|&neighbour| cells.get(neighbour).is_some())
+}
+
+fn random_cell(cells: &Cells, rng: &mut impl RngCore) -> Option<IVec3> {
+    let count = cells.indices().count();
+    (count > 0)
+        .then(|| random_index(count, rng))
+        .and_then(|idx| cells.indices().nth(idx))
+}
+
+fn choose<'a, T>(items: &'a [T], rng: &mut impl RngCore) -> Option<&'a T> {
+    (!items.is_empty())
+        .then(|| random_index(items.len(), rng))
+        .and_then(|idx| items.get(idx))
+}
+
+fn shuffle<T>(items: &mut [T], rng: &mut impl RngCore) {
+    for idx in (1..items.len()).rev() {
+        items.swap(idx, random_index(idx + 1, rng));
+    }
+}
+
+/// Uniform enough for map generation, the modulo bias is negligible for map sized ranges
+#[allow(clippy::cast_possible_truncation)]
+fn random_index(len: usize, rng: &mut impl RngCore) -> usize {
+    (rng.next_u64() % len.max(1) as u64) as usize
+}
+
+/// Random value in `min..=max`, `min` when the range is empty
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_possible_wrap)]
+#[allow(clippy::cast_sign_loss)]
+fn random_range(min: i32, max: i32, rng: &mut impl RngCore) -> i32 {
+    min + random_index((max - min + 1).max(1) as usize, rng) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_prng::WyRand;
+    use rand_core::SeedableRng;
+
+    use super::*;
+    use crate::game_nav_plugin::NavGraph;
+
+    fn assert_faces_in_sync(cells: &Cells) {
+        for indices in cells.indices() {
+            for direction in MAZE_DIRECTIONS {
+                let Some((src_face, dst_face)) = cell::faces_from_move_direction(direction) else {
+                    continue;
+                };
+                let src_open = cells.get_or_open_all(indices).is_open(src_face);
+                match cells.get(indices + direction) {
+                    Some(dst) => {
+                        assert_eq!(src_open, dst.is_open(dst_face), "{indices} {direction}");
+                    }
+                    // Generated mazes are closed on the map edges
+                    None => assert!(!src_open, "{indices} {direction}"),
+                }
+            }
+        }
+    }
+
+    fn open_faces_count(cells: &Cells) -> usize {
+        cells
+            .indices()
+            .flat_map(|indices| [(indices, IVec3::X), (indices, IVec3::Y)])
+            .filter(|&(indices, direction)| {
+                cells.get(indices + direction).is_some() && cells.is_face_open(indices, direction)
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_perfect_mazes() {
+        let mut rng = WyRand::from_seed([7; 8]);
+
+        for algorithm in [
+            MazeAlgorithm::RecursiveBacktracker,
+            MazeAlgorithm::Prim,
+            MazeAlgorithm::Kruskal,
+            MazeAlgorithm::Wilson,
+        ] {
+            let settings = MazeSettings {
+                size: IVec2::new(9, 6),
+                ..MazeSettings::new(algorithm)
+            };
+            let cells = generate_maze(&settings, &mut rng);
+            let nav_graph = NavGraph::from_cells(&cells);
+
+            assert_eq!(cells.size, IVec3::new(9, 6, 1));
+            assert_faces_in_sync(&cells);
+            // A perfect maze is a spanning tree: every cell reachable and no loops
+            assert_eq!(open_faces_count(&cells), 9 * 6 - 1, "{algorithm:?}");
+            for indices in cells.indices() {
+                assert!(
+                    nav_graph.find_path(IVec3::ZERO, indices).is_some(),
+                    "{algorithm:?} {indices}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rooms_and_loops() {
+        let mut rng = WyRand::from_seed([3; 8]);
+        let settings = MazeSettings {
+            size: IVec2::new(16, 10),
+            ..MazeSettings::new(MazeAlgorithm::Rooms)
+        };
+        let cells = generate_maze(&settings, &mut rng);
+        let nav_graph = NavGraph::from_cells(&cells);
+
+        assert_faces_in_sync(&cells);
+        let walkable_cells = cells
+            .indices()
+            .filter(|&indices| is_walkable(&cells, indices))
+            .collect_vec();
+        let Some(&first) = walkable_cells.first() else {
+            panic!("no rooms generated");
+        };
+        for &indices in &walkable_cells {
+            assert!(nav_graph.find_path(first, indices).is_some(), "{indices}");
+        }
+
+        let settings = MazeSettings {
+            loop_density: 0.5,
+            ..MazeSettings::new(MazeAlgorithm::Kruskal)
+        };
+        let cells = generate_maze(&settings, &mut rng);
+        assert_faces_in_sync(&cells);
+        assert!(open_faces_count(&cells) > 16 * 8 - 1);
+    }
+
+    #[test]
+    fn test_same_seed_same_maze() {
+        let settings = MazeSettings::new(MazeAlgorithm::Wilson);
+        let a = generate_maze(&settings, &mut WyRand::from_seed([1; 8]));
+        let b = generate_maze(&settings, &mut WyRand::from_seed([1; 8]));
+
+        assert_eq!(a.array, b.array);
+    }
+
+    #[test]
+    fn test_maze_settings_from_spec() {
+        assert_eq!(
+            MazeSettings::from_spec("prim:24x12:0.25"),
+            Ok(MazeSettings {
+                algorithm: MazeAlgorithm::Prim,
+                size: IVec2::new(24, 12),
+                loop_density: 0.25,
+            })
+        );
+        assert_eq!(
+            MazeSettings::from_spec("rooms"),
+            Ok(MazeSettings::new(MazeAlgorithm::Rooms))
+        );
+        assert!(MazeSettings::from_spec("maze").is_err());
+        assert!(MazeSettings::from_spec("prim:0x4").is_err());
+    }
+
+    #[test]
+    fn test_raid_overlay() {
+        let settings = MazeSettings {
+            size: IVec2::new(4, 3),
+            ..MazeSettings::new(MazeAlgorithm::Kruskal)
+        };
+        let cells = generate_maze(&settings, &mut WyRand::from_seed([5; 8]));
+
+        assert_eq!(raid_overlay(&cells), "..BC\n...B\nRR..");
+    }
+}
@@ -1,28 +1,198 @@
+use std::collections::VecDeque;
+
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use itertools::Itertools;
 
 use crate::{
-    game_cells_plugin::Cells, game_scene_plugin::CellsSpawnedEvent, game_setup_data::MapData,
+    game_cells_plugin::Cells,
+    game_scene_plugin::{CellsChangedEvent, CellsSpawnedEvent},
+    game_setup_data::MapData,
 };
 
+const MOVE_DIRECTIONS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
 #[derive(Debug)]
 pub struct NavPlugin;
 
 impl Plugin for NavPlugin {
     fn build(&self, app: &mut App) {
-        _ = app.add_systems(Update, create_scene);
+        _ = app
+            .init_resource::<NavGraph>()
+            .add_systems(Update, (create_scene, update_nav_graph.after(create_scene)));
+    }
+}
+
+/// Makes a bot follow the shortest path to the given cell instead of wandering
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NavTarget {
+    pub cell_indices: IVec3,
+}
+
+/// Cells reachable in one move from every cell of the map, indexed by cell indices
+#[derive(Resource, Debug, Default)]
+pub struct NavGraph {
+    neighbours: HashMap<IVec3, Vec<IVec3>>,
+}
+
+impl NavGraph {
+    pub fn from_cells(cells: &Cells) -> Self {
+        let mut nav_graph = Self::default();
+        nav_graph.update_cells(cells, cells.indices());
+        nav_graph
+    }
+
+    /// Recomputes the reachable neighbours of the given cells and of the cells around them.
+    pub fn update_cells(&mut self, cells: &Cells, cell_indices: impl IntoIterator<Item = IVec3>) {
+        let affected = cell_indices
+            .into_iter()
+            .flat_map(|indices| {
+                MOVE_DIRECTIONS
+                    .iter()
+                    .map(move |&direction| indices + direction)
+                    .chain([indices])
+            })
+            .unique()
+            .collect_vec();
+
+        for indices in affected {
+            if cells.get(indices).is_none() {
+                continue;
+            }
+            let neighbours = MOVE_DIRECTIONS
+                .iter()
+                .map(|&direction| indices + direction)
+                .filter(|&dst| {
+                    cells.get(dst).is_some() && cells.is_face_open(indices, dst - indices)
+                })
+                .collect_vec();
+            _ = self.neighbours.insert(indices, neighbours);
+        }
+    }
+
+    pub fn neighbours(&self, cell_indices: IVec3) -> &[IVec3] {
+        self.neighbours
+            .get(&cell_indices)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Finds a shortest path between two cells, including both of them.
+    pub fn find_path(
+        &self,
+        src_cell_indices: IVec3,
+        dst_cell_indices: IVec3,
+    ) -> Option<Vec<IVec3>> {
+        let mut came_from = HashMap::from([(src_cell_indices, src_cell_indices)]);
+        let mut frontier = VecDeque::from([src_cell_indices]);
+
+        while let Some(current) = frontier.pop_front() {
+            if current == dst_cell_indices {
+                let mut path = vec![current];
+                let mut step = current;
+                while step != src_cell_indices {
+                    step = *came_from.get(&step)?;
+                    path.push(step);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for &next in self.neighbours(current) {
+                if !came_from.contains_key(&next) {
+                    _ = came_from.insert(next, current);
+                    frontier.push_back(next);
+                }
+            }
+        }
+
+        None
     }
 }
 
 #[allow(clippy::needless_pass_by_value)]
 fn create_scene(
-    _commands: Commands,
+    mut commands: Commands,
     mut cells_spawned_reader: EventReader<CellsSpawnedEvent>,
     cells: Option<Res<Cells>>,
     map_data: Option<Res<MapData>>,
 ) {
-    if let Some(_cells) = cells {
+    if let Some(cells) = cells {
         if let Some(_map_data) = map_data {
-            for CellsSpawnedEvent {} in cells_spawned_reader.read() {}
+            for CellsSpawnedEvent {} in cells_spawned_reader.read() {
+                commands.insert_resource(NavGraph::from_cells(&cells));
+            }
         }
     }
 }
+
+/// Keeps the nav graph in sync with the cells changed at runtime so paths get recomputed on them
+#[allow(clippy::needless_pass_by_value)]
+fn update_nav_graph(
+    mut cells_changed_reader: EventReader<CellsChangedEvent>,
+    mut nav_graph: ResMut<NavGraph>,
+    cells: Option<Res<Cells>>,
+) {
+    if let Some(cells) = cells {
+        for CellsChangedEvent { cell_indices } in cells_changed_reader.read() {
+            nav_graph.update_cells(&cells, cell_indices.iter().copied());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nav_graph_find_path() {
+        #[allow(clippy::non_ascii_literal)]
+        let map_string = "
+╔═╗
+║█║
+╨█╨
+";
+        let mut cells = Cells::from_string(map_string);
+        let nav_graph = NavGraph::from_cells(&cells);
+
+        assert_eq!(
+            nav_graph.find_path(IVec3::new(0, 0, 0), IVec3::new(2, 0, 0)),
+            Some(vec![
+                IVec3::new(0, 0, 0),
+                IVec3::new(0, 1, 0),
+                IVec3::new(0, 2, 0),
+                IVec3::new(1, 2, 0),
+                IVec3::new(2, 2, 0),
+                IVec3::new(2, 1, 0),
+                IVec3::new(2, 0, 0),
+            ])
+        );
+        assert_eq!(
+            nav_graph.find_path(IVec3::new(0, 0, 0), IVec3::new(1, 0, 0)),
+            None
+        );
+
+        let changed = cells.set_face_open(IVec3::new(0, 2, 0), IVec3::X, false);
+        let mut nav_graph = nav_graph;
+        nav_graph.update_cells(&cells, changed);
+
+        assert_eq!(
+            nav_graph.find_path(IVec3::new(0, 0, 0), IVec3::new(2, 0, 0)),
+            None
+        );
+        assert_eq!(
+            nav_graph.find_path(IVec3::new(2, 2, 0), IVec3::new(2, 0, 0)),
+            Some(vec![
+                IVec3::new(2, 2, 0),
+                IVec3::new(2, 1, 0),
+                IVec3::new(2, 0, 0)
+            ])
+        );
+    }
+}
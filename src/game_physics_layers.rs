@@ -5,4 +5,7 @@ pub enum Layer {
     Ground,
     Constructed,
     Bots,
+    Items,
+    RedTeam,
+    BlueTeam,
 }
@@ -0,0 +1,330 @@
+use core::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::game_bots_plugin::Bot;
+use crate::game_cells_plugin::map_overlay_glyphs;
+use crate::game_coordinates_utils::CellCoords;
+use crate::game_map_file::MapInfo;
+use crate::game_nav_plugin::{NavGraph, NavTarget};
+use crate::game_setup_data::MapData;
+use crate::game_teams_plugin::Team;
+
+const RAID_MATCH_DURATION: f32 = 120.;
+const RAID_CAPTURE_DURATION: f32 = 10.;
+
+/// Attackers try to reach and hold the capture cells until captured, defenders block them until the
+/// match timer runs out
+#[derive(Debug)]
+pub struct RaidPlugin {
+    pub attackers: Team,
+    pub defenders: Team,
+    /// Exits the app once the match has a result, for headless bot-vs-bot runs
+    pub exit_on_finish: bool,
+}
+
+impl Default for RaidPlugin {
+    fn default() -> Self {
+        Self {
+            attackers: Team::Red,
+            defenders: Team::Blue,
+            exit_on_finish: false,
+        }
+    }
+}
+
+impl Plugin for RaidPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app
+            .add_event::<RaidFinishedEvent>()
+            .insert_resource(RaidMatch::new(self.attackers, self.defenders))
+            .add_systems(
+                Update,
+                (
+                    raid_orders_system,
+                    raid_capture_system,
+                    raid_finished_system,
+                )
+                    .chain(),
+            );
+
+        if self.exit_on_finish {
+            _ = app.add_systems(
+                Update,
+                exit_on_raid_finished_system.after(raid_finished_system),
+            );
+        }
+    }
+}
+
+/// Cells the attackers have to capture, indexed by cell indices
+#[derive(Resource, Debug, Default)]
+pub struct CaptureCells {
+    pub cell_indices: Vec<IVec3>,
+}
+
+impl CaptureCells {
+    /// Parses the capture cells (`C`) from a map overlay laid out exactly like the cells map string
+    pub fn from_string(overlay_string: &str) -> Self {
+        let cell_indices = map_overlay_glyphs(overlay_string)
+            .filter(|&(_, glyph)| glyph == 'C')
+            .map(|(cell_indices, _)| cell_indices)
+            .collect_vec();
+
+        Self { cell_indices }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RaidResult {
+    AttackersWin,
+    DefendersWin,
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct RaidMatch {
+    pub attackers: Team,
+    pub defenders: Team,
+    pub match_timer: Timer,
+    /// Seconds the capture cells have been held by attackers only
+    pub capture_progress: f32,
+    pub result: Option<RaidResult>,
+}
+
+impl RaidMatch {
+    pub fn new(attackers: Team, defenders: Team) -> Self {
+        Self {
+            attackers,
+            defenders,
+            match_timer: Timer::from_seconds(RAID_MATCH_DURATION, TimerMode::Once),
+            capture_progress: 0.,
+            result: None,
+        }
+    }
+
+    pub fn capture_fraction(&self) -> f32 {
+        (self.capture_progress / RAID_CAPTURE_DURATION).min(1.)
+    }
+
+    /// Advances the match by `delta` with the teams of the bots standing on the capture cells, and
+    /// decides the result once the capture completes or the match timer runs out
+    pub fn advance(&mut self, delta: Duration, teams_on_capture_cells: &[Team]) {
+        // Any defender on the capture cells contests the capture and pauses the progress
+        if teams_on_capture_cells.contains(&self.attackers)
+            && !teams_on_capture_cells.contains(&self.defenders)
+        {
+            self.capture_progress += delta.as_secs_f32();
+        }
+
+        if self.capture_progress >= RAID_CAPTURE_DURATION {
+            self.result = Some(RaidResult::AttackersWin);
+        } else if self.match_timer.tick(delta).just_finished() {
+            self.result = Some(RaidResult::DefendersWin);
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+// Define an event to represent the end of a raid match, the result is the one of the `RaidMatch`
+pub struct RaidFinishedEvent {}
+
+/// Sends attackers to the nearest capture cell and defenders to the corridor cell in front of it. The paths
+/// are only searched again once a bot entered another cell or the cells changed.
+#[allow(clippy::needless_pass_by_value)]
+fn raid_orders_system(
+    mut commands: Commands,
+    mut ordered_bot_cells: Local<Vec<(Entity, IVec3)>>,
+    raid_match: Res<RaidMatch>,
+    capture_cells: Option<Res<CaptureCells>>,
+    nav_graph: Res<NavGraph>,
+    map_data: Option<Res<MapData>>,
+    bots_query: Query<(Entity, &Team, &Transform, Option<&NavTarget>), With<Bot>>,
+) {
+    let (Some(capture_cells), Some(map_data)) = (capture_cells, map_data) else {
+        return;
+    };
+    if raid_match.result.is_some() {
+        return;
+    }
+
+    let bot_cells = bots_query
+        .iter()
+        .map(|(entity, &team, transform, nav_target)| {
+            let cell_indices = CellCoords::from_game_coordinates(transform.translation)
+                .as_cell_indices(&map_data.bounds);
+            (entity, team, cell_indices, nav_target)
+        })
+        .collect_vec();
+
+    let occupied_cells = bot_cells
+        .iter()
+        .map(|&(entity, _, cell_indices, _)| (entity, cell_indices))
+        .collect_vec();
+    if *ordered_bot_cells == occupied_cells
+        && !nav_graph.is_changed()
+        && !capture_cells.is_changed()
+    {
+        return;
+    }
+    *ordered_bot_cells = occupied_cells;
+
+    // Shortest attacker path to any capture cell, the defenders hold the corridor leading into it
+    let attack_paths = bot_cells
+        .iter()
+        .filter(|&&(_, team, _, _)| team == raid_match.attackers)
+        .filter_map(|&(entity, _, cell_indices, _)| {
+            capture_cells
+                .cell_indices
+                .iter()
+                .filter_map(|&capture| nav_graph.find_path(cell_indices, capture))
+                .min_by_key(Vec::len)
+                .map(|path| (entity, path))
+        })
+        .collect_vec();
+
+    let guard_cells = attack_paths
+        .iter()
+        .filter_map(|(_, path)| path.len().checked_sub(2).and_then(|idx| path.get(idx)))
+        .copied()
+        .unique()
+        .collect_vec();
+
+    let mut defender_idx = 0;
+    for &(entity, team, _, nav_target) in &bot_cells {
+        let target = if team == raid_match.attackers {
+            attack_paths
+                .iter()
+                .find(|(attacker, _)| *attacker == entity)
+                .and_then(|(_, path)| path.last())
+                .copied()
+        } else if team == raid_match.defenders {
+            let guard = guard_cells
+                .get(defender_idx % guard_cells.len().max(1))
+                .or_else(|| capture_cells.cell_indices.first())
+                .copied();
+            defender_idx += 1;
+            guard
+        } else {
+            None
+        };
+
+        let nav_target_cell = nav_target.map(|nav_target| nav_target.cell_indices);
+        if let Some(cell_indices) = target.filter(|&target| Some(target) != nav_target_cell) {
+            _ = commands.entity(entity).insert(NavTarget { cell_indices });
+        }
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn raid_capture_system(
+    time: Res<Time>,
+    mut raid_match: ResMut<RaidMatch>,
+    capture_cells: Option<Res<CaptureCells>>,
+    map_data: Option<Res<MapData>>,
+    bots_query: Query<(&Team, &Transform), With<Bot>>,
+) {
+    let (Some(capture_cells), Some(map_data)) = (capture_cells, map_data) else {
+        return;
+    };
+    if raid_match.result.is_some() {
+        return;
+    }
+
+    let teams_on_capture_cells = bots_query
+        .iter()
+        .filter(|(_, transform)| {
+            let cell_indices = CellCoords::from_game_coordinates(transform.translation)
+                .as_cell_indices(&map_data.bounds);
+            capture_cells.cell_indices.contains(&cell_indices)
+        })
+        .map(|(&team, _)| team)
+        .collect_vec();
+
+    raid_match.advance(time.delta(), &teams_on_capture_cells);
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn raid_finished_system(
+    raid_match: Res<RaidMatch>,
+    map_info: Option<Res<MapInfo>>,
+    mut raid_finished_writer: EventWriter<RaidFinishedEvent>,
+) {
+    if raid_match.is_changed() {
+        if let Some(result) = raid_match.result {
+            let par_time = map_info
+                .and_then(|map_info| map_info.par_times.get("raid").copied())
+                .map(|par_time| format!(", par {par_time:.1}s"))
+                .unwrap_or_default();
+            info!(
+                "Raid finished: {result:?} (capture {:.0}%, {:.1}s{par_time})",
+                raid_match.capture_fraction() * 100.,
+                raid_match.match_timer.elapsed_secs()
+            );
+            _ = raid_finished_writer.send(RaidFinishedEvent {});
+        }
+    }
+}
+
+fn exit_on_raid_finished_system(
+    mut raid_finished_reader: EventReader<RaidFinishedEvent>,
+    mut app_exit_writer: EventWriter<AppExit>,
+) {
+    if raid_finished_reader.read().last().is_some() {
+        _ = app_exit_writer.send(AppExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_cells_load_string() {
+        let overlay_string = "
+..C
+C.d
+";
+
+        let capture_cells = CaptureCells::from_string(overlay_string);
+
+        assert_eq!(
+            capture_cells.cell_indices,
+            [IVec3::new(0, 0, 0), IVec3::new(2, 1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_raid_capture_progress() {
+        let mut raid_match = RaidMatch::new(Team::Red, Team::Blue);
+        let second = Duration::from_secs(1);
+
+        raid_match.advance(second * 4, &[Team::Red]);
+        assert!((raid_match.capture_fraction() - 0.4).abs() < 1e-6);
+
+        // Contested and empty capture cells pause the progress
+        raid_match.advance(second * 4, &[Team::Red, Team::Blue]);
+        raid_match.advance(second * 4, &[Team::Blue]);
+        raid_match.advance(second * 4, &[]);
+        assert!((raid_match.capture_fraction() - 0.4).abs() < 1e-6);
+        assert_eq!(raid_match.result, None);
+
+        raid_match.advance(second * 6, &[Team::Red, Team::Red]);
+        assert_eq!(raid_match.result, Some(RaidResult::AttackersWin));
+        assert!((raid_match.capture_fraction() - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_raid_defenders_win_on_timeout() {
+        let mut raid_match = RaidMatch::new(Team::Red, Team::Blue);
+        let match_duration = Duration::from_secs_f32(RAID_MATCH_DURATION);
+
+        raid_match.advance(match_duration / 2, &[Team::Red, Team::Blue]);
+        assert_eq!(raid_match.result, None);
+
+        raid_match.advance(match_duration / 2, &[Team::Red, Team::Blue]);
+        assert_eq!(raid_match.result, Some(RaidResult::DefendersWin));
+    }
+}
@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::resource::GlobalEntropy;
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+
+use crate::game_bots_plugin::{BotLogicState, BotLogicUpdateTimer, BotProgram, BotSpawner};
+use crate::game_items_plugin::Item;
+use crate::game_map_file::MapFile;
+use crate::game_raid_plugin::RaidMatch;
+use crate::game_teams_plugin::Team;
+
+/// Current version of the RON save file format
+pub const SAVE_FILE_VERSION: u32 = 1;
+
+/// A snapshot of the whole simulation, restored exactly as it was saved
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveFile {
+    pub version: u32,
+    /// The map as it was when saved: the current cells, the items still lying around and the doors
+    pub map: MapFile,
+    /// Elapsed seconds of every door timer, in the order of the map doors, 0 for switch doors
+    #[serde(default)]
+    pub door_timers: Vec<f32>,
+    pub bots: Vec<SavedBot>,
+    pub bot_spawner: BotSpawner,
+    pub bot_logic_timer: BotLogicUpdateTimer,
+    #[serde(default)]
+    pub team_programs: Vec<(Team, BotProgram)>,
+    #[serde(default)]
+    pub team_scores: Vec<(Team, u32)>,
+    /// Saved only while a raid is running, and restored only into a raid
+    #[serde(default)]
+    pub raid: Option<RaidMatch>,
+    pub simulation_tick: u64,
+    pub rng: GlobalEntropy<WyRand>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SavedBot {
+    pub team: Team,
+    pub program: BotProgram,
+    pub transform: Transform,
+    pub linear_velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub logic_state: BotLogicState,
+    #[serde(default)]
+    pub items: Vec<Item>,
+    /// Cell indices the bot navigates to
+    #[serde(default)]
+    pub nav_target: Option<IVec3>,
+}
+
+impl SaveFile {
+    /// Writes the snapshot with the glyph grid of the map as a literal block, like the map files
+    pub fn to_ron_string(&self) -> Result<String, String> {
+        let without_cells = Self {
+            map: MapFile {
+                cells: String::new(),
+                ..self.map.clone()
+            },
+            ..self.clone()
+        };
+        let ron_string = to_string_pretty(&without_cells, PrettyConfig::default())
+            .map_err(|error| error.to_string())?;
+
+        let cells_literal = format!("cells: \"\n{}\n\"", self.map.cells.trim_matches('\n'));
+        Ok(ron_string.replacen("cells: \"\"", &cells_literal, 1))
+    }
+}
+
+/// Just the version of a save file, checked first as newer versions may not parse at all
+#[derive(Deserialize)]
+struct SaveFileVersion {
+    version: u32,
+}
+
+pub fn parse_save_file(save_string: &str) -> Result<SaveFile, String> {
+    let SaveFileVersion { version } =
+        ron::from_str(save_string).map_err(|error| error.to_string())?;
+    if version > SAVE_FILE_VERSION {
+        return Err(format!(
+            "save file version {version} is newer than the supported version {SAVE_FILE_VERSION}"
+        ));
+    }
+    let save_file: SaveFile = ron::from_str(save_string).map_err(|error| error.to_string())?;
+    save_file.map.validate()?;
+    if let Some(elapsed) = save_file
+        .door_timers
+        .iter()
+        .find(|&&elapsed| Duration::try_from_secs_f32(elapsed).is_err())
+    {
+        return Err(format!("invalid door timer of {elapsed} elapsed seconds"));
+    }
+    Ok(save_file)
+}
+
+pub fn load_save_file(path: &Path) -> Result<SaveFile, String> {
+    let save_string = fs::read_to_string(path)
+        .map_err(|error| format!("failed to read save {}: {error}", path.display()))?;
+    parse_save_file(&save_string)
+        .map_err(|error| format!("failed to parse save {}: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::{RngCore, SeedableRng};
+
+    use super::*;
+    use crate::game_map_file::MAP_FILE_VERSION;
+
+    #[test]
+    #[allow(clippy::non_ascii_literal)]
+    fn test_save_file_round_trip() {
+        let mut rng = GlobalEntropy::<WyRand>::from_seed(7_u64.to_le_bytes());
+        _ = rng.next_u64();
+        let mut save_file = SaveFile {
+            version: SAVE_FILE_VERSION,
+            map: MapFile {
+                version: MAP_FILE_VERSION,
+                cells: "╞═╗\n╞═╝".to_owned(),
+                items: vec![(IVec3::new(1, 1, 0), Item::Key)],
+                ..default()
+            },
+            door_timers: vec![],
+            bots: vec![SavedBot {
+                team: Team::Red,
+                program: BotProgram::Level1,
+                transform: Transform::from_xyz(1., 0.25, 0.)
+                    .with_rotation(Quat::from_rotation_z(1.)),
+                linear_velocity: Vec3::new(0.5, -0.25, 0.),
+                angular_velocity: Vec3::ZERO,
+                logic_state: BotLogicState {
+                    ticks: 3,
+                    next_cell_indices: Some(IVec3::X),
+                    ..default()
+                },
+                items: vec![Item::DataChip],
+                nav_target: Some(IVec3::new(2, 1, 0)),
+            }],
+            bot_spawner: BotSpawner::default(),
+            bot_logic_timer: BotLogicUpdateTimer::default(),
+            team_programs: vec![(Team::Red, BotProgram::Level1)],
+            team_scores: vec![(Team::Red, 3)],
+            raid: Some(RaidMatch::new(Team::Red, Team::Blue)),
+            simulation_tick: 42,
+            rng,
+        };
+
+        let ron_string = save_file
+            .to_ron_string()
+            .unwrap_or_else(|error| panic!("{error}"));
+        let mut round_trip = parse_save_file(&ron_string).unwrap_or_else(|error| panic!("{error}"));
+
+        assert!(ron_string.contains(&save_file.map.cells));
+        assert_eq!(round_trip.map.cells.trim_matches('\n'), save_file.map.cells);
+        assert_eq!(round_trip.map.items, save_file.map.items);
+        assert_eq!(round_trip.bots, save_file.bots);
+        assert_eq!(round_trip.team_scores, save_file.team_scores);
+        assert_eq!(round_trip.simulation_tick, 42);
+        assert!(round_trip.raid.is_some());
+        // The generator continues where it was saved
+        assert_eq!(round_trip.rng.next_u64(), save_file.rng.next_u64());
+
+        save_file.door_timers = vec![-3.];
+        let ron_string = save_file
+            .to_ron_string()
+            .unwrap_or_else(|error| panic!("{error}"));
+        assert!(
+            parse_save_file(&ron_string).is_err_and(|error| error.contains("invalid door timer"))
+        );
+    }
+
+    #[test]
+    fn test_parse_save_file_newer_version() {
+        assert!(parse_save_file("(version: 2)")
+            .is_err_and(|error| error.contains("newer than the supported version")));
+    }
+}
@@ -0,0 +1,337 @@
+use core::time::Duration;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::resource::GlobalEntropy;
+use bevy_xpbd_3d::prelude::*;
+use itertools::Itertools;
+
+use crate::game_bots_plugin::{
+    spawn_bot_with_transform, Bot, BotLogicState, BotLogicUpdateTimer, BotProgram, BotSpawnedEvent,
+    BotSpawner, TeamPrograms,
+};
+use crate::game_cells_plugin::Cells;
+use crate::game_coordinates_utils::CellCoords;
+use crate::game_doors_plugin::{Door, DoorPlacements, DoorTrigger};
+use crate::game_items_plugin::{Inventory, Item, ItemPlacements};
+use crate::game_map_file::{MapFile, MapInfo};
+use crate::game_nav_plugin::NavTarget;
+use crate::game_raid_plugin::{CaptureCells, RaidMatch};
+use crate::game_save_file::{load_save_file, SaveFile, SavedBot, SAVE_FILE_VERSION};
+use crate::game_scene_plugin::{insert_map_resources, CellsSpawnedEvent};
+use crate::game_setup_data::MapData;
+use crate::game_teams_plugin::{SpawnPoints, Team, TeamCollisionRules, TeamScores};
+use crate::game_time_plugin::SimulationTick;
+use crate::input_actions_plugin::ButtonAction;
+
+/// Snapshots the whole simulation to a RON save file and restores it, quick saving and loading with the
+/// `QuickSave` and `QuickLoad` actions
+#[derive(Debug)]
+pub struct SaveStatePlugin {
+    /// RON save file written by the quick save and read by the quick load
+    pub save_path: PathBuf,
+    /// Save file restored in place of the spawned map when the app starts
+    pub load_path: Option<PathBuf>,
+}
+
+impl Default for SaveStatePlugin {
+    fn default() -> Self {
+        Self {
+            save_path: PathBuf::from("saves/quicksave.ron"),
+            load_path: None,
+        }
+    }
+}
+
+impl Plugin for SaveStatePlugin {
+    fn build(&self, app: &mut App) {
+        _ = app
+            .add_event::<SaveStateEvent>()
+            .add_event::<LoadStateEvent>()
+            .insert_resource(QuickSave {
+                path: self.save_path.clone(),
+            })
+            // Loaded before the `CellsSpawnedEvent` listeners run, so they build the loaded map
+            .add_systems(PreUpdate, load_state_system)
+            // Saved once the frame is simulated
+            .add_systems(Last, save_state_system)
+            .add_systems(
+                Update,
+                quick_save_actions_system.run_if(resource_exists::<ButtonInput<ButtonAction>>),
+            );
+
+        if let Some(path) = &self.load_path {
+            _ = app.world.send_event(LoadStateEvent { path: path.clone() });
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct SaveStateEvent {
+    pub path: PathBuf,
+}
+
+/// Replaces the map, the bots and the simulation state with the ones of a save file
+#[derive(Event, Debug)]
+pub struct LoadStateEvent {
+    pub path: PathBuf,
+}
+
+#[derive(Resource, Debug)]
+struct QuickSave {
+    path: PathBuf,
+}
+
+/// The simulation resources written to a save file
+#[derive(SystemParam)]
+struct SimulationResources<'w> {
+    cells: Option<Res<'w, Cells>>,
+    map_data: Option<Res<'w, MapData>>,
+    map_info: Option<Res<'w, MapInfo>>,
+    spawn_points: Option<Res<'w, SpawnPoints>>,
+    capture_cells: Option<Res<'w, CaptureCells>>,
+    bot_spawner: Res<'w, BotSpawner>,
+    bot_logic_timer: Res<'w, BotLogicUpdateTimer>,
+    team_programs: Res<'w, TeamPrograms>,
+    team_scores: Res<'w, TeamScores>,
+    raid_match: Option<Res<'w, RaidMatch>>,
+    simulation_tick: Res<'w, SimulationTick>,
+    rng: Res<'w, GlobalEntropy<WyRand>>,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn quick_save_actions_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    quick_save: Res<QuickSave>,
+    mut save_state_writer: EventWriter<SaveStateEvent>,
+    mut load_state_writer: EventWriter<LoadStateEvent>,
+) {
+    if button_actions.just_pressed(ButtonAction::QuickSave) {
+        _ = save_state_writer.send(SaveStateEvent {
+            path: quick_save.path.clone(),
+        });
+    }
+    if button_actions.just_pressed(ButtonAction::QuickLoad) {
+        _ = load_state_writer.send(LoadStateEvent {
+            path: quick_save.path.clone(),
+        });
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::type_complexity)]
+fn save_state_system(
+    mut save_state_reader: EventReader<SaveStateEvent>,
+    resources: SimulationResources,
+    bots_query: Query<
+        (
+            Entity,
+            &Team,
+            &BotProgram,
+            &Transform,
+            &LinearVelocity,
+            &AngularVelocity,
+            &BotLogicState,
+            &Inventory,
+            Option<&NavTarget>,
+        ),
+        With<Bot>,
+    >,
+    items_query: Query<(Entity, &Item, &Transform)>,
+    doors_query: Query<(Entity, &Door)>,
+) {
+    for SaveStateEvent { path } in save_state_reader.read() {
+        let (Some(cells), Some(map_data), Some(map_info), Some(spawn_points), Some(capture_cells)) = (
+            &resources.cells,
+            &resources.map_data,
+            &resources.map_info,
+            &resources.spawn_points,
+            &resources.capture_cells,
+        ) else {
+            error!("Failed to save to {}: no map spawned yet", path.display());
+            continue;
+        };
+
+        // Sorted by entity so the bots, items and doors are spawned back in the same order
+        let item_placements = ItemPlacements {
+            items: items_query
+                .iter()
+                .sorted_by_key(|&(entity, ..)| entity)
+                .map(|(_, &item, transform)| {
+                    let cell_indices = CellCoords::from_game_coordinates(transform.translation)
+                        .as_cell_indices(&map_data.bounds);
+                    (cell_indices, item)
+                })
+                .collect(),
+        };
+        let door_placements = DoorPlacements {
+            doors: doors_query
+                .iter()
+                .sorted_by_key(|&(entity, _)| entity)
+                .map(|(_, door)| door.clone())
+                .collect(),
+        };
+        let door_timers = door_placements
+            .doors
+            .iter()
+            .map(|door| match &door.trigger {
+                DoorTrigger::Timer(timer) => timer.elapsed_secs(),
+                DoorTrigger::Switch { .. } => 0.,
+            })
+            .collect();
+        let bots = bots_query
+            .iter()
+            .sorted_by_key(|&(entity, ..)| entity)
+            .map(
+                |(
+                    _,
+                    &team,
+                    &program,
+                    &transform,
+                    linear_velocity,
+                    angular_velocity,
+                    logic_state,
+                    inventory,
+                    nav_target,
+                )| SavedBot {
+                    team,
+                    program,
+                    transform,
+                    linear_velocity: linear_velocity.0,
+                    angular_velocity: angular_velocity.0,
+                    logic_state: logic_state.clone(),
+                    items: inventory.items.clone(),
+                    nav_target: nav_target.map(|nav_target| nav_target.cell_indices),
+                },
+            )
+            .collect();
+
+        let save_file = SaveFile {
+            version: SAVE_FILE_VERSION,
+            map: MapFile::from_spawned_map(
+                cells,
+                map_data,
+                map_info,
+                spawn_points,
+                &item_placements,
+                capture_cells,
+                &door_placements,
+            ),
+            door_timers,
+            bots,
+            bot_spawner: resources.bot_spawner.clone(),
+            bot_logic_timer: resources.bot_logic_timer.clone(),
+            team_programs: Team::ALL
+                .into_iter()
+                .filter_map(|team| Some((team, *resources.team_programs.programs.get(&team)?)))
+                .collect(),
+            team_scores: Team::ALL
+                .into_iter()
+                .filter_map(|team| Some((team, *resources.team_scores.scores.get(&team)?)))
+                .collect(),
+            raid: resources.raid_match.as_deref().cloned(),
+            simulation_tick: resources.simulation_tick.0,
+            rng: resources.rng.clone(),
+        };
+
+        let saved = write_save_file(path, &save_file);
+        match saved {
+            Ok(()) => info!("Simulation saved to {}", path.display()),
+            Err(error) => error!("Failed to save to {}: {error}", path.display()),
+        }
+    }
+}
+
+fn write_save_file(path: &Path, save_file: &SaveFile) -> Result<(), String> {
+    let ron_string = save_file.to_ron_string()?;
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(path, ron_string))
+        .map_err(|error| error.to_string())
+}
+
+/// Despawns the bots, items and doors, then respawns them from the save file along with the map. The map
+/// spawned on startup, if not built yet, is dropped for the loaded one.
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+fn load_state_system(
+    mut commands: Commands,
+    mut load_state_reader: EventReader<LoadStateEvent>,
+    mut cells_spawned_events: ResMut<Events<CellsSpawnedEvent>>,
+    mut bot_spawned_writer: EventWriter<BotSpawnedEvent>,
+    collision_rules: Res<TeamCollisionRules>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    raid_match: Option<ResMut<RaidMatch>>,
+    spawned_query: Query<Entity, Or<(With<Bot>, With<Item>, With<Door>)>>,
+) {
+    let Some(LoadStateEvent { path }) = load_state_reader.read().last() else {
+        return;
+    };
+    let save_file = match load_save_file(path) {
+        Ok(save_file) => save_file,
+        Err(error) => {
+            error!("{error}");
+            return;
+        }
+    };
+
+    for entity in &spawned_query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let mut map_source = save_file.map.into_map_source();
+    for (door, &elapsed) in map_source.doors.iter_mut().zip(&save_file.door_timers) {
+        if let DoorTrigger::Timer(timer) = &mut door.trigger {
+            timer.set_elapsed(Duration::from_secs_f32(elapsed));
+        }
+    }
+    let cells = Cells::from_string(&map_source.cells_string);
+    _ = insert_map_resources(&mut commands, cells, "", &map_source);
+    cells_spawned_events.clear();
+    _ = cells_spawned_events.send(CellsSpawnedEvent {});
+
+    for bot in save_file.bots {
+        let entity = spawn_bot_with_transform(
+            commands.reborrow(),
+            bot.transform,
+            bot.team,
+            bot.program,
+            &collision_rules,
+        );
+        let mut entity_commands = commands.entity(entity);
+        _ = entity_commands.insert((
+            LinearVelocity(bot.linear_velocity),
+            AngularVelocity(bot.angular_velocity),
+            bot.logic_state,
+            Inventory { items: bot.items },
+        ));
+        if let Some(cell_indices) = bot.nav_target {
+            _ = entity_commands.insert(NavTarget { cell_indices });
+        }
+        _ = bot_spawned_writer.send(BotSpawnedEvent {
+            entity,
+            transform: bot.transform,
+            team: bot.team,
+        });
+    }
+
+    commands.insert_resource(save_file.bot_spawner);
+    commands.insert_resource(save_file.bot_logic_timer);
+    commands.insert_resource(TeamPrograms {
+        programs: save_file.team_programs.into_iter().collect(),
+    });
+    commands.insert_resource(TeamScores {
+        scores: save_file.team_scores.into_iter().collect(),
+    });
+    commands.insert_resource(SimulationTick(save_file.simulation_tick));
+    *rng = save_file.rng;
+    if let (Some(mut raid_match), Some(saved_raid_match)) = (raid_match, save_file.raid) {
+        *raid_match = saved_raid_match;
+    }
+
+    info!("Simulation loaded from {}", path.display());
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::game_bots_plugin::BotProgram;
+use crate::game_map_file::{load_map_file, Objective};
+use crate::game_maze_generator::MazeSettings;
+use crate::game_scene_plugin::MapSource;
+use crate::game_teams_plugin::Team;
+
+/// Current version of the RON scenario file format
+pub const SCENARIO_FILE_VERSION: u32 = 1;
+
+/// A hand written test setup: the map, the seed, the bots placed on it, and how long it runs
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ScenarioFile {
+    pub version: u32,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub map: ScenarioMap,
+    /// Seeds the global entropy, and so the generated mazes
+    #[serde(default)]
+    pub seed: u64,
+    /// Bots placed on startup, the map spawn points are not used
+    #[serde(default)]
+    pub bots: Vec<ScenarioBot>,
+    /// Objectives on top of the ones of the map
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+    /// Fixed ticks the scenario runs for, it runs until stopped when `None`
+    #[serde(default)]
+    pub tick_limit: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ScenarioMap {
+    /// The built-in map
+    #[default]
+    Builtin,
+    /// Map file, relative to the scenario file
+    File(PathBuf),
+    /// Maze spec as given to `--maze`, generated from the scenario seed
+    Maze(String),
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ScenarioBot {
+    /// Cell indices the bot starts on
+    pub cell: IVec3,
+    /// Move direction the bot faces, along x or y
+    #[serde(default = "default_heading")]
+    pub heading: IVec3,
+    pub team: Team,
+    /// The program of the team when `None`
+    #[serde(default)]
+    pub program: Option<BotProgram>,
+}
+
+/// Bots spawned at a map spawn point face the top of the map
+const fn default_heading() -> IVec3 {
+    IVec3::Y
+}
+
+impl ScenarioFile {
+    /// The map of the scenario with its objectives, map files are looked up relative to `base_dir`
+    pub fn map_source(&self, base_dir: &Path) -> Result<MapSource, String> {
+        let mut map_source = match &self.map {
+            ScenarioMap::Builtin => MapSource::default(),
+            ScenarioMap::File(path) => load_map_file(&base_dir.join(path).to_string_lossy())?,
+            ScenarioMap::Maze(spec) => MapSource::from_maze(MazeSettings::from_spec(spec)?),
+        };
+
+        map_source.capture_cells.extend(
+            self.objectives
+                .iter()
+                .map(|&Objective::Capture(cell_indices)| cell_indices),
+        );
+        Ok(map_source)
+    }
+}
+
+/// Just the version of a scenario file, checked first as newer versions may not parse at all
+#[derive(Deserialize)]
+struct ScenarioFileVersion {
+    version: u32,
+}
+
+pub fn parse_scenario_file(scenario_string: &str) -> Result<ScenarioFile, String> {
+    let ScenarioFileVersion { version } =
+        ron::from_str(scenario_string).map_err(|error| error.to_string())?;
+    if version > SCENARIO_FILE_VERSION {
+        return Err(format!(
+            "scenario file version {version} is newer than the supported version {SCENARIO_FILE_VERSION}"
+        ));
+    }
+
+    let scenario_file: ScenarioFile =
+        ron::from_str(scenario_string).map_err(|error| error.to_string())?;
+    if let Some(bot) = scenario_file.bots.iter().find(|bot| {
+        bot.heading.z != 0
+            || bot.heading.abs().max_element() != 1
+            || bot.heading.length_squared() != 1
+    }) {
+        return Err(format!(
+            "bot heading {} at cell {} is not along x or y",
+            bot.heading, bot.cell
+        ));
+    }
+    Ok(scenario_file)
+}
+
+pub fn load_scenario_file(path: &Path) -> Result<ScenarioFile, String> {
+    let scenario_string = fs::read_to_string(path)
+        .map_err(|error| format!("failed to read scenario {}: {error}", path.display()))?;
+    parse_scenario_file(&scenario_string)
+        .map_err(|error| format!("failed to parse scenario {}: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::indexing_slicing)]
+    fn test_parse_scenario_file() {
+        let scenario_string = r#"
+(
+    version: 1,
+    name: "Corridor duel",
+    map: Maze("prim:6x4"),
+    seed: 7,
+    bots: [
+        (cell: (0, 0, 0), team: Red),
+        (cell: (5, 3, 0), heading: (-1, 0, 0), team: Blue, program: Some(Level1)),
+    ],
+    objectives: [Capture((5, 0, 0))],
+    tick_limit: Some(600),
+)
+"#;
+
+        let scenario_file =
+            parse_scenario_file(scenario_string).unwrap_or_else(|error| panic!("{error}"));
+
+        assert_eq!(scenario_file.map, ScenarioMap::Maze("prim:6x4".to_owned()));
+        assert_eq!(scenario_file.seed, 7);
+        assert_eq!(scenario_file.tick_limit, Some(600));
+        assert_eq!(scenario_file.bots.len(), 2);
+        assert_eq!(scenario_file.bots[0].heading, IVec3::Y);
+        assert_eq!(scenario_file.bots[0].program, None);
+        assert_eq!(scenario_file.bots[1].program, Some(BotProgram::Level1));
+
+        let map_source = scenario_file
+            .map_source(Path::new("."))
+            .unwrap_or_else(|error| panic!("{error}"));
+        assert!(map_source.maze.is_some());
+        assert_eq!(map_source.capture_cells, [IVec3::new(5, 0, 0)]);
+    }
+
+    #[test]
+    fn test_parse_scenario_file_errors() {
+        assert!(parse_scenario_file("(version: 2)")
+            .is_err_and(|error| error.contains("newer than the supported version")));
+        assert!(parse_scenario_file(
+            "(version: 1, bots: [(cell: (0, 0, 0), heading: (1, 1, 0), team: Red)])"
+        )
+        .is_err_and(|error| error.contains("not along x or y")));
+    }
+
+    #[test]
+    fn test_bundled_scenario() {
+        let scenario_file =
+            parse_scenario_file(include_str!("../assets/scenarios/spiral_duel.ron"))
+                .unwrap_or_else(|error| panic!("{error}"));
+
+        let map_source = scenario_file
+            .map_source(Path::new("assets/scenarios"))
+            .unwrap_or_else(|error| panic!("{error}"));
+        assert_eq!(map_source.info.name, "Spiral");
+        assert_eq!(map_source.capture_cells.len(), 1);
+        assert!(scenario_file.tick_limit.is_some());
+    }
+}
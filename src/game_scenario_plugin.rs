@@ -0,0 +1,117 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::resource::GlobalEntropy;
+
+use crate::game_bots_plugin::{spawn_bot_with_transform, BotSpawnedEvent, TeamPrograms};
+use crate::game_coordinates_utils::CellCoords;
+use crate::game_scenario_file::ScenarioBot;
+use crate::game_scene_plugin::{spawn_map, CellsSpawnedEvent, MapSource, SkipStartupSpawn};
+use crate::game_teams_plugin::TeamCollisionRules;
+use crate::game_time_plugin::SimulationTick;
+
+/// Builds the world from a scenario in place of the map and bots spawned on startup, then stops the
+/// simulation once its tick limit is reached
+#[derive(Debug)]
+pub struct ScenarioPlugin {
+    pub scenario: Scenario,
+    /// Exits the app at the tick limit instead of pausing, for headless runs
+    pub exit_on_finish: bool,
+}
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app
+            .insert_resource(self.scenario.clone())
+            // The scenario spawns its own map and bots
+            .init_resource::<SkipStartupSpawn>()
+            .add_systems(Startup, spawn_scenario_system)
+            .add_systems(Update, scenario_tick_limit_system);
+
+        if self.exit_on_finish {
+            _ = app.add_systems(
+                Update,
+                exit_on_tick_limit_system.after(scenario_tick_limit_system),
+            );
+        }
+    }
+}
+
+/// The loaded scenario, with its map resolved
+#[derive(Resource, Clone, Debug)]
+pub struct Scenario {
+    pub name: String,
+    pub map_source: MapSource,
+    pub bots: Vec<ScenarioBot>,
+    pub tick_limit: Option<u64>,
+    /// Set once the tick limit is reached
+    pub finished: bool,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn spawn_scenario_system(
+    mut commands: Commands,
+    scenario: Res<Scenario>,
+    mut cells_spawned_writer: EventWriter<CellsSpawnedEvent>,
+    mut bot_spawned_writer: EventWriter<BotSpawnedEvent>,
+    collision_rules: Res<TeamCollisionRules>,
+    team_programs: Res<TeamPrograms>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+) {
+    if !scenario.name.is_empty() {
+        info!("Starting scenario {}", scenario.name);
+    }
+    let map_data = spawn_map(&mut commands, &scenario.map_source, rng.as_mut());
+    _ = cells_spawned_writer.send(CellsSpawnedEvent {});
+
+    let map_size = map_data.bounds.size();
+    for bot in &scenario.bots {
+        if bot.cell.cmplt(IVec3::ZERO).any() || bot.cell.cmpge(map_size).any() {
+            warn!("Scenario bot at cell {} is outside the map", bot.cell);
+            continue;
+        }
+
+        let transform = CellCoords::from_cell_indices(bot.cell, &map_data.bounds)
+            .as_game_coordinates_transform()
+            .looking_to(bot.heading.as_vec3(), Vec3::Z);
+        let program = bot
+            .program
+            .unwrap_or_else(|| team_programs.program(bot.team));
+        let entity = spawn_bot_with_transform(
+            commands.reborrow(),
+            transform,
+            bot.team,
+            program,
+            &collision_rules,
+        );
+        _ = bot_spawned_writer.send(BotSpawnedEvent {
+            entity,
+            transform,
+            team: bot.team,
+        });
+    }
+}
+
+/// Pauses the simulation on the tick limit, the fixed ticks of the frame that reached it may overshoot it
+#[allow(clippy::needless_pass_by_value)]
+fn scenario_tick_limit_system(
+    mut scenario: ResMut<Scenario>,
+    simulation_tick: Res<SimulationTick>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    let Some(tick_limit) = scenario.tick_limit else {
+        return;
+    };
+    if !scenario.finished && simulation_tick.0 >= tick_limit {
+        scenario.finished = true;
+        virtual_time.pause();
+        info!("Scenario finished: tick limit {tick_limit} reached");
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn exit_on_tick_limit_system(scenario: Res<Scenario>, mut app_exit_writer: EventWriter<AppExit>) {
+    if scenario.is_changed() && scenario.finished {
+        _ = app_exit_writer.send(AppExit);
+    }
+}
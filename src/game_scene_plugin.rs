@@ -4,8 +4,17 @@ use bevy::{
     ecs::system::Commands,
 };
 
+use bevy_prng::WyRand;
+use bevy_rand::resource::GlobalEntropy;
+
 use crate::game_cells_plugin::Cells;
+use crate::game_doors_plugin::{Door, DoorPlacements, DoorTrigger};
+use crate::game_items_plugin::{Item, ItemPlacements};
+use crate::game_map_file::MapInfo;
+use crate::game_maze_generator::{generate_maze, raid_overlay, MazeSettings};
+use crate::game_raid_plugin::CaptureCells;
 use crate::game_setup_data::MapData;
+use crate::game_teams_plugin::{SpawnPoints, Team};
 use crate::ibounds3::IBounds3;
 
 #[derive(Debug)]
@@ -15,10 +24,20 @@ impl Plugin for SceneElementsPlugin {
     fn build(&self, app: &mut App) {
         _ = app
             .add_event::<CellsSpawnedEvent>()
-            .add_systems(Startup, spawn_scene_cells);
+            .add_event::<CellsChangedEvent>()
+            .init_resource::<MapSource>()
+            .add_systems(
+                Startup,
+                spawn_scene_cells.run_if(not(resource_exists::<SkipStartupSpawn>)),
+            );
     }
 }
 
+/// Skips spawning the map on startup and the bots on the spawn points, for plugins building the world themselves
+/// (e.g. scenarios)
+#[derive(Resource, Debug, Default)]
+pub struct SkipStartupSpawn;
+
 #[derive(Event, Debug)]
 // Define an event to represent the spawning of a bot
 pub struct CellsSpawnedEvent {
@@ -26,14 +45,14 @@ pub struct CellsSpawnedEvent {
     // pub map_data: MapData,
 }
 
-#[allow(clippy::cast_possible_wrap)]
-#[allow(clippy::cast_precision_loss)]
-fn spawn_scene_cells(
-    mut commands: Commands,
-    mut cells_spawned_writer: EventWriter<CellsSpawnedEvent>,
-) {
-    #[allow(clippy::non_ascii_literal)]
-    let cells_string = "
+#[derive(Event, Debug)]
+// Define an event to represent cells being modified after they were spawned
+pub struct CellsChangedEvent {
+    pub cell_indices: Vec<IVec3>,
+}
+
+#[allow(clippy::non_ascii_literal)]
+const DEFAULT_CELLS_STRING: &str = "
 █╞╦╗╔╦╩╡╚══════╗
 ╞═╬╣╠╬╦╡╔══════╝
 ██║║╠╣║█║╔═════╗
@@ -44,14 +63,209 @@ fn spawn_scene_cells(
 ████╔╗██╚══════╝
 ";
 
-    let cells = Cells::from_string(cells_string);
+// Overlay uses the same layout as the cells above
+// Items: d = data chip, k = key, b = battery
+// Spawn points: R = red team, B = blue team
+// Raid: C = capture cell
+const DEFAULT_OVERLAY_STRING: &str = "
+.........R.R..d.
+...k............
+................
+......b.........
+.............C..
+................
+.............d..
+.....d....B.B...
+";
+
+/// Separates the cells from the overlay in a map file
+pub const MAP_OVERLAY_SEPARATOR: &str = "---";
+
+/// The map spawned on startup, the built-in map unless replaced before the app runs
+#[derive(Resource, Clone, Debug)]
+pub struct MapSource {
+    pub cells_string: String,
+    pub overlay_string: String,
+    pub doors: Vec<Door>,
+    /// Generates the cells and the raid overlay from the global entropy instead of the strings
+    pub maze: Option<MazeSettings>,
+    pub info: MapInfo,
+    /// Game coordinates the center of the map is placed at
+    pub origin: IVec3,
+    /// Placements on top of the ones parsed from the overlay
+    pub spawn_points: Vec<(IVec3, Team)>,
+    pub items: Vec<(IVec3, Item)>,
+    pub capture_cells: Vec<IVec3>,
+}
+
+impl Default for MapSource {
+    fn default() -> Self {
+        Self {
+            cells_string: DEFAULT_CELLS_STRING.to_owned(),
+            overlay_string: DEFAULT_OVERLAY_STRING.to_owned(),
+            doors: vec![
+                Door::new(
+                    IVec3::new(8, 0, 0),
+                    IVec3::X,
+                    DoorTrigger::Timer(Timer::from_seconds(3., TimerMode::Repeating)),
+                ),
+                Door::new(
+                    IVec3::new(0, 4, 0),
+                    IVec3::X,
+                    DoorTrigger::Switch {
+                        switch_cell_indices: IVec3::new(1, 6, 0),
+                    },
+                ),
+            ],
+            maze: None,
+            info: MapInfo {
+                name: "Spiral".to_owned(),
+                author: "CodeRaid".to_owned(),
+                ..default()
+            },
+            origin: IVec3::ZERO,
+            spawn_points: vec![],
+            items: vec![],
+            capture_cells: vec![],
+        }
+    }
+}
+
+impl MapSource {
+    /// Parses a map file: the cells string, optionally followed by a `---` line and the overlay
+    pub fn from_map_string(map_string: &str) -> Self {
+        let separator = format!("\n{MAP_OVERLAY_SEPARATOR}\n");
+        let mut parts = map_string.splitn(2, &separator);
+        let cells_string = parts.next().unwrap_or_default();
+        let overlay_string = parts.next().unwrap_or_default();
+
+        Self {
+            cells_string: cells_string.trim_matches('\n').to_owned(),
+            overlay_string: overlay_string.trim_matches('\n').to_owned(),
+            ..Self::empty()
+        }
+    }
+
+    pub fn from_maze(maze: MazeSettings) -> Self {
+        Self {
+            maze: Some(maze),
+            ..Self::empty()
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            cells_string: String::new(),
+            overlay_string: String::new(),
+            doors: vec![],
+            maze: None,
+            info: MapInfo::default(),
+            origin: IVec3::ZERO,
+            spawn_points: vec![],
+            items: vec![],
+            capture_cells: vec![],
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_wrap)]
+#[allow(clippy::cast_precision_loss)]
+#[allow(clippy::needless_pass_by_value)]
+fn spawn_scene_cells(
+    mut commands: Commands,
+    mut cells_spawned_writer: EventWriter<CellsSpawnedEvent>,
+    map_source: Res<MapSource>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+) {
+    _ = spawn_map(&mut commands, &map_source, rng.as_mut());
+
+    _ = cells_spawned_writer.send(CellsSpawnedEvent {});
+}
+
+/// Builds the cells of the map source, generating the maze if it is one, and inserts the map resources.
+/// Returns the map data the bots are placed with.
+pub fn spawn_map(
+    commands: &mut Commands,
+    map_source: &MapSource,
+    rng: &mut GlobalEntropy<WyRand>,
+) -> MapData {
+    let (cells, overlay_string) = map_source.maze.as_ref().map_or_else(
+        || {
+            (
+                Cells::from_string(&map_source.cells_string),
+                map_source.overlay_string.clone(),
+            )
+        },
+        |maze| {
+            let cells = generate_maze(maze, rng);
+            let overlay_string = raid_overlay(&cells);
+            (cells, overlay_string)
+        },
+    );
+    insert_map_resources(commands, cells, &overlay_string, map_source)
+}
+
+/// Inserts the cells and everything placed on them, the placements parsed from the overlay followed by the
+/// ones listed in the map source. Spawning the map is left to the `CellsSpawnedEvent` listeners.
+pub fn insert_map_resources(
+    commands: &mut Commands,
+    cells: Cells,
+    overlay_string: &str,
+    map_source: &MapSource,
+) -> MapData {
+    let mut item_placements = ItemPlacements::from_string(overlay_string);
+    item_placements.items.extend(&map_source.items);
+    let mut spawn_points = SpawnPoints::from_string(overlay_string);
+    spawn_points.spawn_points.extend(&map_source.spawn_points);
+    let mut capture_cells = CaptureCells::from_string(overlay_string);
+    capture_cells.cell_indices.extend(&map_source.capture_cells);
+    let door_placements = DoorPlacements {
+        doors: map_source.doors.clone(),
+    };
 
     let map_data = MapData {
-        bounds: IBounds3::new(IVec3::ZERO, cells.size),
+        bounds: IBounds3::new(map_source.origin, cells.size),
     };
 
     commands.insert_resource(cells);
     commands.insert_resource(map_data);
+    commands.insert_resource(item_placements);
+    commands.insert_resource(door_placements);
+    commands.insert_resource(spawn_points);
+    commands.insert_resource(capture_cells);
+    if !map_source.info.name.is_empty() {
+        info!(
+            "Spawning map {} by {}",
+            map_source.info.name, map_source.info.author
+        );
+    }
+    commands.insert_resource(map_source.info.clone());
+    map_data
+}
 
-    _ = cells_spawned_writer.send(CellsSpawnedEvent {});
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::non_ascii_literal)]
+    fn test_map_source_from_map_string() {
+        let map_string = "
+╔╗
+╚╝
+---
+R.
+.C
+";
+
+        let map_source = MapSource::from_map_string(map_string);
+
+        assert_eq!(map_source.cells_string, "╔╗\n╚╝");
+        assert_eq!(map_source.overlay_string, "R.\n.C");
+        assert!(map_source.doors.is_empty());
+
+        let map_source = MapSource::from_map_string("╔╗\n╚╝\n");
+        assert_eq!(map_source.cells_string, "╔╗\n╚╝");
+        assert!(map_source.overlay_string.is_empty());
+    }
 }
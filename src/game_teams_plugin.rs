@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_xpbd_3d::prelude::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::game_cells_plugin::map_overlay_glyphs;
+use crate::game_items_plugin::ItemPickedUpEvent;
+use crate::game_physics_layers::Layer;
+
+#[derive(Debug)]
+pub struct TeamsPlugin;
+
+impl Plugin for TeamsPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app
+            .init_resource::<TeamScores>()
+            .init_resource::<TeamCollisionRules>()
+            .add_systems(Update, team_scoring_system);
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Team {
+    Red,
+    Blue,
+}
+
+impl Team {
+    pub const ALL: [Self; 2] = [Self::Red, Self::Blue];
+
+    /// Parses a team spawn point glyph from the map overlay
+    pub const fn from_spawn_char(spawn_char: char) -> Option<Self> {
+        match spawn_char {
+            'R' => Some(Self::Red),
+            'B' => Some(Self::Blue),
+            _ => None,
+        }
+    }
+
+    pub const fn color(self) -> Color {
+        match self {
+            Self::Red => Color::rgb(0.9, 0.6, 0.6),
+            Self::Blue => Color::rgb(0.6, 0.7, 0.9),
+        }
+    }
+
+    pub const fn light_color(self) -> Color {
+        match self {
+            Self::Red => Color::rgb(1.0, 0.3, 0.3),
+            Self::Blue => Color::rgb(0.3, 0.5, 1.0),
+        }
+    }
+
+    pub const fn layer(self) -> Layer {
+        match self {
+            Self::Red => Layer::RedTeam,
+            Self::Blue => Layer::BlueTeam,
+        }
+    }
+
+    /// Collision layers for a bot of this team, bots of the same team pass through each other when
+    /// `friendly_pass_through` is set
+    pub fn bot_collision_layers(self, rules: &TeamCollisionRules) -> CollisionLayers {
+        // Bots collides with ground, and constructed layers and pick up items
+        let mut filters = LayerMask::from([Layer::Ground, Layer::Constructed, Layer::Items]);
+        if rules.friendly_pass_through {
+            for team in Self::ALL.into_iter().filter(|&team| team != self) {
+                filters.add(team.layer());
+            }
+        } else {
+            filters.add(Layer::Bots);
+        }
+
+        CollisionLayers::new([Layer::Bots, self.layer()], filters)
+    }
+}
+
+/// Per team bot spawn points on the map, indexed by cell indices
+#[derive(Resource, Debug, Default)]
+pub struct SpawnPoints {
+    pub spawn_points: Vec<(IVec3, Team)>,
+}
+
+impl SpawnPoints {
+    /// Parses the spawn points from a map overlay laid out exactly like the cells map string
+    pub fn from_string(overlay_string: &str) -> Self {
+        let spawn_points = map_overlay_glyphs(overlay_string)
+            .filter_map(|(cell_indices, glyph)| {
+                Team::from_spawn_char(glyph).map(|team| (cell_indices, team))
+            })
+            .collect_vec();
+
+        Self { spawn_points }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct TeamCollisionRules {
+    pub friendly_pass_through: bool,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct TeamScores {
+    pub scores: HashMap<Team, u32>,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn team_scoring_system(
+    mut item_picked_up_reader: EventReader<ItemPickedUpEvent>,
+    mut team_scores: ResMut<TeamScores>,
+    bots_query: Query<&Team>,
+) {
+    for event in item_picked_up_reader.read() {
+        if let Ok(&team) = bots_query.get(event.bot) {
+            *team_scores.scores.entry(team).or_default() += event.item.score();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn_points_load_string() {
+        let overlay_string = "
+R..B
+..d.
+B...
+";
+
+        let spawn_points = SpawnPoints::from_string(overlay_string);
+
+        assert_eq!(
+            spawn_points.spawn_points,
+            [
+                (IVec3::new(0, 0, 0), Team::Blue),
+                (IVec3::new(0, 2, 0), Team::Red),
+                (IVec3::new(3, 2, 0), Team::Blue),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bot_collision_layers_friendly_pass_through() {
+        let rules = TeamCollisionRules {
+            friendly_pass_through: true,
+        };
+        let red = Team::Red.bot_collision_layers(&rules);
+        let blue = Team::Blue.bot_collision_layers(&rules);
+
+        assert!(red.interacts_with(blue));
+        assert!(!red.interacts_with(red));
+
+        let rules = TeamCollisionRules::default();
+        let red = Team::Red.bot_collision_layers(&rules);
+
+        assert!(red.interacts_with(red));
+    }
+}
@@ -0,0 +1,191 @@
+use bevy::prelude::*;
+use bevy::time::TimeSystem;
+
+use crate::input_actions_plugin::ButtonAction;
+
+/// Slowest and fastest the simulation runs relative to real time
+pub const MIN_TIME_SCALE: f64 = 0.1;
+pub const MAX_TIME_SCALE: f64 = 16.;
+/// Factor one speed up or slow down scales the time by
+const TIME_SCALE_STEP: f64 = 2.;
+
+/// Pauses, steps and scales the simulation time. Everything simulated reads the virtual time, physics through
+/// the fixed ticks it accumulates, so scaling the virtual time scales them all alike.
+#[derive(Debug)]
+pub struct TimeControlPlugin {
+    pub time_scale: f64,
+    pub paused: bool,
+}
+
+impl Default for TimeControlPlugin {
+    fn default() -> Self {
+        Self {
+            time_scale: 1.,
+            paused: false,
+        }
+    }
+}
+
+impl Plugin for TimeControlPlugin {
+    fn build(&self, app: &mut App) {
+        let mut virtual_time = app.world.resource_mut::<Time<Virtual>>();
+        virtual_time.set_relative_speed_f64(clamp_time_scale(self.time_scale));
+        if self.paused {
+            virtual_time.pause();
+        }
+
+        _ = app
+            .add_event::<TimeControlEvent>()
+            .init_resource::<TimeControl>()
+            .init_resource::<SimulationTick>()
+            .add_systems(First, time_control_system.after(TimeSystem))
+            .add_systems(FixedFirst, simulation_tick_system)
+            .add_systems(
+                Update,
+                time_control_actions_system.run_if(resource_exists::<ButtonInput<ButtonAction>>),
+            );
+    }
+}
+
+#[derive(Event, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeControlEvent {
+    TogglePause,
+    /// Pauses and runs a single fixed tick
+    Step,
+    SpeedUp,
+    SlowDown,
+}
+
+/// Fixed ticks run since the start, the physics and the bots simulation steps
+#[derive(Resource, Debug, Default)]
+pub struct SimulationTick(pub u64);
+
+#[derive(Resource, Debug, Default)]
+struct TimeControl {
+    /// Fixed ticks still to run one frame after the other while paused
+    pending_steps: u32,
+}
+
+const fn clamp_time_scale(time_scale: f64) -> f64 {
+    time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE)
+}
+
+fn simulation_tick_system(mut simulation_tick: ResMut<SimulationTick>) {
+    simulation_tick.0 += 1;
+}
+
+/// Applies the time control events, runs right after the clocks are updated so a step is seen by the whole
+/// frame: the virtual time advances by exactly one fixed timestep, which runs exactly one fixed tick
+#[allow(clippy::needless_pass_by_value)]
+fn time_control_system(
+    mut time_control_reader: EventReader<TimeControlEvent>,
+    mut time_control: ResMut<TimeControl>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    fixed_time: Res<Time<Fixed>>,
+    mut time: ResMut<Time>,
+) {
+    for &event in time_control_reader.read() {
+        match event {
+            TimeControlEvent::TogglePause => {
+                if virtual_time.is_paused() {
+                    virtual_time.unpause();
+                } else {
+                    virtual_time.pause();
+                }
+                time_control.pending_steps = 0;
+            }
+            TimeControlEvent::Step => {
+                virtual_time.pause();
+                time_control.pending_steps += 1;
+            }
+            TimeControlEvent::SpeedUp | TimeControlEvent::SlowDown => {
+                let factor = if event == TimeControlEvent::SpeedUp {
+                    TIME_SCALE_STEP
+                } else {
+                    TIME_SCALE_STEP.recip()
+                };
+                let time_scale = clamp_time_scale(virtual_time.relative_speed_f64() * factor);
+                virtual_time.set_relative_speed_f64(time_scale);
+            }
+        }
+    }
+
+    if time_control.pending_steps > 0 && virtual_time.is_paused() {
+        time_control.pending_steps -= 1;
+        virtual_time.advance_by(fixed_time.timestep());
+        *time = virtual_time.as_generic();
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn time_control_actions_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    mut time_control_writer: EventWriter<TimeControlEvent>,
+) {
+    for (action, event) in [
+        (ButtonAction::Pause, TimeControlEvent::TogglePause),
+        (ButtonAction::Step, TimeControlEvent::Step),
+        (ButtonAction::SpeedUp, TimeControlEvent::SpeedUp),
+        (ButtonAction::SlowDown, TimeControlEvent::SlowDown),
+    ] {
+        if button_actions.just_pressed(action) {
+            _ = time_control_writer.send(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    fn time_control_app() -> App {
+        let mut app = App::new();
+        _ = app
+            .add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                10,
+            )))
+            .add_plugins(TimeControlPlugin {
+                paused: true,
+                ..default()
+            });
+        app
+    }
+
+    #[test]
+    fn test_step_runs_one_fixed_tick() {
+        let mut app = time_control_app();
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(app.world.resource::<SimulationTick>().0, 0);
+
+        _ = app.world.send_event(TimeControlEvent::Step);
+        app.update();
+        assert_eq!(app.world.resource::<SimulationTick>().0, 1);
+        app.update();
+        assert_eq!(app.world.resource::<SimulationTick>().0, 1);
+    }
+
+    #[test]
+    fn test_time_scale_is_clamped() {
+        let mut app = time_control_app();
+        for _ in 0..10 {
+            _ = app.world.send_event(TimeControlEvent::SpeedUp);
+        }
+        app.update();
+        let relative_speed = app.world.resource::<Time<Virtual>>().relative_speed_f64();
+        assert!((relative_speed - MAX_TIME_SCALE).abs() < 1e-9);
+
+        for _ in 0..20 {
+            _ = app.world.send_event(TimeControlEvent::SlowDown);
+        }
+        app.update();
+        let relative_speed = app.world.resource::<Time<Virtual>>().relative_speed_f64();
+        assert!((relative_speed - MIN_TIME_SCALE).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,512 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Pixels scrolled by touchpads per scroll line
+const PIXELS_PER_SCROLL_LINE: f32 = 100.;
+/// Scroll lines per second while a zoom binding is held
+const HELD_ZOOM_LINES_PER_SECOND: f32 = 10.;
+
+/// Turns the keyboard, mouse and gamepad input into camera and selection actions, through bindings read from
+/// a RON file
+#[derive(Debug)]
+pub struct InputActionsPlugin {
+    /// RON bindings file, the default bindings are used if it is missing
+    pub bindings_path: PathBuf,
+}
+
+impl Default for InputActionsPlugin {
+    fn default() -> Self {
+        Self {
+            bindings_path: PathBuf::from("assets/input.ron"),
+        }
+    }
+}
+
+impl Plugin for InputActionsPlugin {
+    fn build(&self, app: &mut App) {
+        let bindings = fs::read_to_string(&self.bindings_path).map_or_else(
+            |_| InputBindings::default(),
+            |bindings_string| {
+                parse_bindings(&bindings_string).unwrap_or_else(|error| {
+                    error!(
+                        "Failed to parse input bindings {}: {error}",
+                        self.bindings_path.display()
+                    );
+                    InputBindings::default()
+                })
+            },
+        );
+
+        _ = app
+            .insert_resource(bindings)
+            .init_resource::<ActionAxes>()
+            .init_resource::<ButtonInput<ButtonAction>>()
+            .add_systems(PreUpdate, input_actions_system.after(InputSystem));
+    }
+}
+
+/// Actions triggered once per press
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ButtonAction {
+    /// Picks the bot under the cursor
+    Select,
+    CycleBots,
+    ClearSelection,
+    FirstPerson,
+    FrameMap,
+    FrameLevel,
+    TopDown,
+    /// Moves the camera target a level up
+    LevelUp,
+    LevelDown,
+    /// Pauses or resumes the simulation
+    Pause,
+    /// Runs a single simulation tick
+    Step,
+    SlowDown,
+    SpeedUp,
+    /// Shows or hides the HUD panels
+    ToggleHud,
+    /// Shows or hides the collider wireframes
+    TogglePhysicsDebug,
+    /// Shows or hides the cell grid overlay
+    ToggleCellGrid,
+    /// Saves the simulation to the quick save file
+    QuickSave,
+    /// Restores the simulation from the quick save file
+    QuickLoad,
+    /// Turns the map editor on or off
+    EditorToggle,
+    /// Edits the level above
+    EditorLevelUp,
+    EditorLevelDown,
+    /// Reverts the last map edit
+    Undo,
+    /// Applies the last reverted map edit again
+    Redo,
+    /// Writes the edited map to its map file
+    EditorSave,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GamepadStick {
+    Left,
+    Right,
+}
+
+impl GamepadStick {
+    const fn axes(self) -> (GamepadAxisType, GamepadAxisType) {
+        match self {
+            Self::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            Self::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        }
+    }
+}
+
+/// Bindings of a two dimensional action, each direction counting as a full stick tilt
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AxisBindings {
+    #[serde(default)]
+    pub up: Vec<Binding>,
+    #[serde(default)]
+    pub down: Vec<Binding>,
+    #[serde(default)]
+    pub left: Vec<Binding>,
+    #[serde(default)]
+    pub right: Vec<Binding>,
+    #[serde(default)]
+    pub stick: Option<GamepadStick>,
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct InputBindings {
+    pub pan: AxisBindings,
+    pub orbit: AxisBindings,
+    /// Held to orbit with the orbit bindings, pan bindings do not pan meanwhile. Sticks ignore it.
+    #[serde(default)]
+    pub orbit_modifier: Vec<Binding>,
+    /// Held to pan with the mouse motion
+    #[serde(default)]
+    pub pan_drag: Vec<Binding>,
+    /// Held to orbit with the mouse motion
+    #[serde(default)]
+    pub orbit_drag: Vec<Binding>,
+    /// Held to zoom in, on top of the scroll wheel
+    #[serde(default)]
+    pub zoom_in: Vec<Binding>,
+    #[serde(default)]
+    pub zoom_out: Vec<Binding>,
+    #[serde(default)]
+    pub buttons: BTreeMap<ButtonAction, Vec<Binding>>,
+    /// Held to trigger the command bindings, pan and orbit bindings do not pan or orbit meanwhile
+    #[serde(default)]
+    pub command_modifier: Vec<Binding>,
+    /// Actions triggered with the command modifier held, e.g. Ctrl+Z
+    #[serde(default)]
+    pub commands: BTreeMap<ButtonAction, Vec<Binding>>,
+}
+
+impl Default for InputBindings {
+    #[allow(clippy::too_many_lines)]
+    fn default() -> Self {
+        let wasd = || AxisBindings {
+            up: vec![Binding::Key(KeyCode::KeyW)],
+            down: vec![Binding::Key(KeyCode::KeyS)],
+            left: vec![Binding::Key(KeyCode::KeyA)],
+            right: vec![Binding::Key(KeyCode::KeyD)],
+            stick: None,
+        };
+
+        Self {
+            pan: AxisBindings {
+                stick: Some(GamepadStick::Left),
+                ..wasd()
+            },
+            orbit: AxisBindings {
+                stick: Some(GamepadStick::Right),
+                ..wasd()
+            },
+            orbit_modifier: vec![Binding::Key(KeyCode::ShiftLeft)],
+            pan_drag: vec![Binding::Mouse(MouseButton::Middle)],
+            orbit_drag: vec![Binding::Mouse(MouseButton::Right)],
+            zoom_in: vec![Binding::Gamepad(GamepadButtonType::RightTrigger2)],
+            zoom_out: vec![Binding::Gamepad(GamepadButtonType::LeftTrigger2)],
+            buttons: BTreeMap::from([
+                (
+                    ButtonAction::Select,
+                    vec![Binding::Mouse(MouseButton::Left)],
+                ),
+                (
+                    ButtonAction::CycleBots,
+                    vec![
+                        Binding::Key(KeyCode::Tab),
+                        Binding::Gamepad(GamepadButtonType::RightTrigger),
+                    ],
+                ),
+                (
+                    ButtonAction::ClearSelection,
+                    vec![
+                        Binding::Key(KeyCode::Escape),
+                        Binding::Gamepad(GamepadButtonType::East),
+                    ],
+                ),
+                (
+                    ButtonAction::FirstPerson,
+                    vec![
+                        Binding::Key(KeyCode::KeyV),
+                        Binding::Gamepad(GamepadButtonType::North),
+                    ],
+                ),
+                (
+                    ButtonAction::FrameMap,
+                    vec![
+                        Binding::Key(KeyCode::KeyF),
+                        Binding::Gamepad(GamepadButtonType::Select),
+                    ],
+                ),
+                (
+                    ButtonAction::FrameLevel,
+                    vec![
+                        Binding::Key(KeyCode::KeyG),
+                        Binding::Gamepad(GamepadButtonType::West),
+                    ],
+                ),
+                (
+                    ButtonAction::TopDown,
+                    vec![
+                        Binding::Key(KeyCode::KeyT),
+                        Binding::Gamepad(GamepadButtonType::Start),
+                    ],
+                ),
+                (
+                    ButtonAction::LevelUp,
+                    vec![
+                        Binding::Key(KeyCode::BracketRight),
+                        Binding::Gamepad(GamepadButtonType::DPadUp),
+                    ],
+                ),
+                (
+                    ButtonAction::LevelDown,
+                    vec![
+                        Binding::Key(KeyCode::BracketLeft),
+                        Binding::Gamepad(GamepadButtonType::DPadDown),
+                    ],
+                ),
+                (
+                    ButtonAction::Pause,
+                    vec![
+                        Binding::Key(KeyCode::Space),
+                        Binding::Gamepad(GamepadButtonType::Mode),
+                    ],
+                ),
+                (
+                    ButtonAction::Step,
+                    vec![
+                        Binding::Key(KeyCode::Period),
+                        Binding::Gamepad(GamepadButtonType::LeftTrigger),
+                    ],
+                ),
+                (
+                    ButtonAction::SlowDown,
+                    vec![
+                        Binding::Key(KeyCode::Minus),
+                        Binding::Gamepad(GamepadButtonType::DPadLeft),
+                    ],
+                ),
+                (
+                    ButtonAction::SpeedUp,
+                    vec![
+                        Binding::Key(KeyCode::Equal),
+                        Binding::Gamepad(GamepadButtonType::DPadRight),
+                    ],
+                ),
+                (ButtonAction::ToggleHud, vec![Binding::Key(KeyCode::F1)]),
+                (
+                    ButtonAction::TogglePhysicsDebug,
+                    vec![Binding::Key(KeyCode::F3)],
+                ),
+                (
+                    ButtonAction::ToggleCellGrid,
+                    vec![Binding::Key(KeyCode::F4)],
+                ),
+                (ButtonAction::QuickSave, vec![Binding::Key(KeyCode::F5)]),
+                (ButtonAction::QuickLoad, vec![Binding::Key(KeyCode::F9)]),
+                (ButtonAction::EditorToggle, vec![Binding::Key(KeyCode::F2)]),
+                (
+                    ButtonAction::EditorLevelUp,
+                    vec![Binding::Key(KeyCode::PageUp)],
+                ),
+                (
+                    ButtonAction::EditorLevelDown,
+                    vec![Binding::Key(KeyCode::PageDown)],
+                ),
+            ]),
+            command_modifier: vec![
+                Binding::Key(KeyCode::ControlLeft),
+                Binding::Key(KeyCode::ControlRight),
+            ],
+            commands: BTreeMap::from([
+                (ButtonAction::Undo, vec![Binding::Key(KeyCode::KeyZ)]),
+                (ButtonAction::Redo, vec![Binding::Key(KeyCode::KeyY)]),
+                (ButtonAction::EditorSave, vec![Binding::Key(KeyCode::KeyS)]),
+            ]),
+        }
+    }
+}
+
+pub fn parse_bindings(bindings_string: &str) -> Result<InputBindings, String> {
+    ron::from_str(bindings_string).map_err(|error| error.to_string())
+}
+
+/// Two dimensional actions of the frame. Keys and sticks count one per frame at full tilt, the mouse motion
+/// counts its pixels, the zoom counts scroll lines.
+#[derive(Resource, Debug, Default)]
+pub struct ActionAxes {
+    pub pan: Vec2,
+    pub orbit: Vec2,
+    /// Positive zooms in
+    pub zoom: f32,
+}
+
+/// Every input device the bindings can refer to
+#[derive(SystemParam)]
+struct BoundInputs<'w> {
+    keyboard_input: Res<'w, ButtonInput<KeyCode>>,
+    mouse_button_input: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_button_input: Res<'w, ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl BoundInputs<'_> {
+    fn pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key_code) => self.keyboard_input.pressed(key_code),
+            Binding::Mouse(mouse_button) => self.mouse_button_input.pressed(mouse_button),
+            Binding::Gamepad(button_type) => self.gamepads.iter().any(|gamepad| {
+                self.gamepad_button_input
+                    .pressed(GamepadButton::new(gamepad, button_type))
+            }),
+        }
+    }
+
+    fn any_pressed(&self, bindings: &[Binding]) -> bool {
+        bindings.iter().any(|&binding| self.pressed(binding))
+    }
+
+    /// Sum of the stick of every gamepad, the gamepad settings dead zones already applied
+    fn stick(&self, stick: GamepadStick) -> Vec2 {
+        let (x_axis, y_axis) = stick.axes();
+        let axis = |gamepad, axis_type| {
+            self.gamepad_axes
+                .get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.)
+        };
+        self.gamepads
+            .iter()
+            .map(|gamepad| Vec2::new(axis(gamepad, x_axis), axis(gamepad, y_axis)))
+            .sum()
+    }
+
+    fn axis(&self, axis_bindings: &AxisBindings, include_buttons: bool) -> Vec2 {
+        let stick = axis_bindings
+            .stick
+            .map_or(Vec2::ZERO, |stick| self.stick(stick));
+        if !include_buttons {
+            return stick;
+        }
+
+        let direction = |bindings: &[Binding]| f32::from(u8::from(self.any_pressed(bindings)));
+        stick
+            + Vec2::new(
+                direction(&axis_bindings.right) - direction(&axis_bindings.left),
+                direction(&axis_bindings.up) - direction(&axis_bindings.down),
+            )
+    }
+}
+
+/// The only reader of the mouse motion and wheel events, everything else reads the actions
+#[allow(clippy::needless_pass_by_value)]
+fn input_actions_system(
+    time: Res<Time<Real>>,
+    bindings: Res<InputBindings>,
+    inputs: BoundInputs,
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mut action_axes: ResMut<ActionAxes>,
+    mut button_actions: ResMut<ButtonInput<ButtonAction>>,
+) {
+    let orbiting =
+        bindings.orbit_modifier.is_empty() || inputs.any_pressed(&bindings.orbit_modifier);
+    let panning = bindings.orbit_modifier.is_empty() || !orbiting;
+    // Command chords like Ctrl+S must not pan or orbit with their key
+    let commanding = inputs.any_pressed(&bindings.command_modifier);
+
+    // Screen motion, dragging right moves the view left
+    let mouse_motion = mouse_motion_events
+        .read()
+        .map(|event| Vec2::new(-event.delta.x, event.delta.y))
+        .sum::<Vec2>();
+    let drag = |bindings: &[Binding]| {
+        if inputs.any_pressed(bindings) {
+            mouse_motion
+        } else {
+            Vec2::ZERO
+        }
+    };
+
+    let scroll = mouse_wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_SCROLL_LINE,
+        })
+        .sum::<f32>();
+    let held_zoom = f32::from(u8::from(inputs.any_pressed(&bindings.zoom_in)))
+        - f32::from(u8::from(inputs.any_pressed(&bindings.zoom_out)));
+
+    *action_axes = ActionAxes {
+        pan: inputs.axis(&bindings.pan, panning && !commanding) + drag(&bindings.pan_drag),
+        orbit: inputs.axis(&bindings.orbit, orbiting && !commanding) + drag(&bindings.orbit_drag),
+        zoom: (held_zoom * HELD_ZOOM_LINES_PER_SECOND).mul_add(time.delta_seconds(), scroll),
+    };
+
+    button_actions.clear();
+    let pressed_actions = bindings
+        .buttons
+        .iter()
+        .filter(|(_, action_bindings)| inputs.any_pressed(action_bindings))
+        .chain(
+            bindings
+                .commands
+                .iter()
+                .filter(|(_, action_bindings)| commanding && inputs.any_pressed(action_bindings)),
+        )
+        .map(|(&action, _)| action)
+        .collect::<BTreeSet<_>>();
+    for &action in bindings.buttons.keys().chain(bindings.commands.keys()) {
+        if pressed_actions.contains(&action) {
+            button_actions.press(action);
+        } else {
+            button_actions.release(action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::InputPlugin;
+
+    use super::*;
+
+    #[test]
+    fn test_bundled_bindings_match_default_bindings() {
+        let bindings = parse_bindings(include_str!("../assets/input.ron"))
+            .unwrap_or_else(|error| panic!("{error}"));
+
+        assert_eq!(bindings, InputBindings::default());
+    }
+
+    #[test]
+    fn test_parse_bindings_defaults_missing_fields() {
+        let bindings = parse_bindings(
+            "(pan: (up: [Key(ArrowUp)], stick: Some(Left)), orbit: (), buttons: {Select: [Gamepad(South)]})",
+        )
+        .unwrap_or_else(|error| panic!("{error}"));
+
+        assert_eq!(bindings.pan.up, [Binding::Key(KeyCode::ArrowUp)]);
+        assert_eq!(bindings.pan.stick, Some(GamepadStick::Left));
+        assert!(bindings.orbit_modifier.is_empty());
+        assert_eq!(
+            bindings.buttons.get(&ButtonAction::Select),
+            Some(&vec![Binding::Gamepad(GamepadButtonType::South)])
+        );
+    }
+
+    #[test]
+    fn test_command_chord_does_not_pan() {
+        fn press(app: &mut App, key_codes: &[KeyCode]) {
+            let mut keyboard_input = app.world.resource_mut::<ButtonInput<KeyCode>>();
+            keyboard_input.release_all();
+            for &key_code in key_codes {
+                keyboard_input.press(key_code);
+            }
+            app.update();
+        }
+
+        let mut app = App::new();
+        _ = app
+            .add_plugins((MinimalPlugins, InputPlugin))
+            .add_plugins(InputActionsPlugin {
+                bindings_path: PathBuf::from("missing.ron"),
+            });
+
+        press(&mut app, &[KeyCode::KeyS]);
+        assert_eq!(app.world.resource::<ActionAxes>().pan, Vec2::NEG_Y);
+        assert!(!app
+            .world
+            .resource::<ButtonInput<ButtonAction>>()
+            .pressed(ButtonAction::EditorSave));
+
+        press(&mut app, &[KeyCode::ControlLeft, KeyCode::KeyS]);
+        assert_eq!(app.world.resource::<ActionAxes>().pan, Vec2::ZERO);
+        assert!(app
+            .world
+            .resource::<ButtonInput<ButtonAction>>()
+            .just_pressed(ButtonAction::EditorSave));
+    }
+}
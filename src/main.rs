@@ -6,46 +6,319 @@ mod ibounds3;
 mod game_bots_plugin;
 mod game_cells_plugin;
 mod game_coordinates_utils;
+mod game_doors_plugin;
+mod game_items_plugin;
+mod game_map_file;
+mod game_maze_generator;
+#[allow(dead_code)]
+mod game_mover_plugin;
 mod game_nav_plugin;
 mod game_physics_layers;
 mod game_physics_plugin;
+mod game_raid_plugin;
+mod game_save_file;
+mod game_save_plugin;
+mod game_scenario_file;
+mod game_scenario_plugin;
 mod game_scene_plugin;
 mod game_setup_data;
-mod game_mover_plugin;
+mod game_teams_plugin;
+mod game_time_plugin;
+
+mod tile_classifier;
+mod tournament;
 
 mod camera_controller_plugin;
 mod camera_setup_plugin;
+mod editor_plugin;
+mod input_actions_plugin;
 
+mod visual_assets_plugin;
+mod visual_bot_debug_plugin;
 mod visual_bots_plugin;
+mod visual_debug_plugin;
+mod visual_hud_plugin;
+mod visual_items_plugin;
+mod visual_minimap_plugin;
+mod visual_picking;
 mod visual_scene_plugin;
 
+use core::time::Duration;
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
 use bevy::prelude::PluginGroup;
 use bevy::utils::default;
 use bevy::{
-    app::App,
-    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    app::{App, ScheduleRunnerPlugin},
+    asset::{AssetApp, AssetPlugin},
+    diagnostic::FrameTimeDiagnosticsPlugin,
+    hierarchy::HierarchyPlugin,
+    log::LogPlugin,
+    pbr::StandardMaterial,
     render::{
+        mesh::Mesh,
         settings::{Backends, RenderCreation, WgpuSettings},
         RenderPlugin,
     },
+    scene::ScenePlugin,
+    time::TimeUpdateStrategy,
+    transform::TransformPlugin,
     window::{Window, WindowPlugin},
-    DefaultPlugins,
+    DefaultPlugins, MinimalPlugins,
 };
 
 use bevy_prng::WyRand;
 use bevy_rand::prelude::EntropyPlugin;
 use game_bots_plugin::BotsPlugin;
+use game_doors_plugin::DoorsPlugin;
+use game_items_plugin::ItemsPlugin;
+use game_nav_plugin::NavPlugin;
+use game_raid_plugin::RaidPlugin;
+use game_save_plugin::SaveStatePlugin;
+use game_scenario_file::load_scenario_file;
+use game_scenario_plugin::{Scenario, ScenarioPlugin};
+use game_teams_plugin::TeamsPlugin;
+use game_time_plugin::{TimeControlPlugin, MAX_TIME_SCALE, MIN_TIME_SCALE};
+use rand_core::SeedableRng;
 
 use camera_controller_plugin::CameraControllerPlugin;
 use camera_setup_plugin::CameraSetupPlugin;
+use editor_plugin::EditorPlugin;
+use game_cells_plugin::GlyphSet;
+use game_map_file::{convert_map_file, load_map_file, parse_map_file};
+use game_maze_generator::{generate_maze, raid_overlay, MazeSettings};
 use game_physics_plugin::PhysicsPlugin;
-use game_scene_plugin::SceneElementsPlugin;
+use game_scene_plugin::{MapSource, SceneElementsPlugin};
 use game_setup_data::GameSetupData;
+use input_actions_plugin::InputActionsPlugin;
+use visual_assets_plugin::VisualAssetsPlugin;
+use visual_bot_debug_plugin::VisualBotDebugPlugin;
 use visual_bots_plugin::VisualBotsPlugin;
+use visual_debug_plugin::VisualDebugPlugin;
+use visual_hud_plugin::VisualHudPlugin;
+use visual_items_plugin::VisualItemsPlugin;
+use visual_minimap_plugin::VisualMinimapPlugin;
+use visual_picking::PickingPlugin;
 use visual_scene_plugin::VisualSceneElementsPlugin;
 
+/// Simulation step used when running without a window, independent of the wall clock
+const HEADLESS_UPDATE_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
 fn main() {
-    App::new()
+    if env::args().nth(1).as_deref() == Some("tournament") {
+        if let Err(error) = tournament::run(env::args().skip(2)) {
+            eprintln!("{error}");
+            process::exit(2);
+        }
+        return;
+    }
+    if env::args().nth(1).as_deref() == Some("convert-map") {
+        if let Err(error) = print_converted_map(env::args().skip(2)) {
+            eprintln!("{error}");
+            process::exit(2);
+        }
+        return;
+    }
+    if env::args().nth(1).as_deref() == Some("maze") {
+        if let Err(error) = print_maze(env::args().skip(2)) {
+            eprintln!("{error}");
+            process::exit(2);
+        }
+        return;
+    }
+
+    let headless = env::args().any(|arg| arg == "--headless");
+    let raid = env::args().any(|arg| arg == "--raid");
+    let map_source = match map_source_from_args() {
+        Ok(map_source) => map_source,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(2);
+        }
+    };
+    let scenario = match scenario_from_args() {
+        Ok(scenario) => scenario,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(2);
+        }
+    };
+    let time_control = match time_control_from_args(headless) {
+        Ok(time_control) => time_control,
+        Err(error) => {
+            eprintln!("{error}");
+            process::exit(2);
+        }
+    };
+
+    let mut app = App::new();
+    if headless {
+        add_headless_plugins(&mut app);
+        _ = app.add_plugins(LogPlugin::default());
+    } else {
+        add_windowed_plugins(&mut app);
+    }
+
+    _ = app.add_plugins(
+        scenario
+            .as_ref()
+            .map_or_else(EntropyPlugin::<WyRand>::default, |(seed, _)| {
+                EntropyPlugin::<WyRand>::with_seed(seed.to_le_bytes())
+            }),
+    );
+    add_game_plugins(&mut app);
+    _ = app.add_plugins(time_control);
+    _ = app.add_plugins(save_state_from_args());
+
+    if let Some(map_source) = map_source {
+        _ = app.insert_resource(map_source);
+    }
+    if let Some((_, scenario)) = scenario {
+        _ = app.add_plugins(ScenarioPlugin {
+            scenario,
+            exit_on_finish: headless,
+        });
+    }
+    if raid {
+        _ = app.add_plugins(RaidPlugin {
+            exit_on_finish: headless,
+            ..default()
+        });
+    }
+
+    app.run();
+}
+
+/// Map picked with `--map <file>` or `--maze <algorithm>[:<w>x<h>[:<loop density>]]`, the built-in map otherwise
+fn map_source_from_args() -> Result<Option<MapSource>, String> {
+    match (arg_value("--map"), arg_value("--maze")) {
+        (Some(path), _) => load_map_file(&path).map(Some),
+        (None, Some(spec)) => {
+            MazeSettings::from_spec(&spec).map(|maze| Some(MapSource::from_maze(maze)))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// Scenario picked with `--scenario <file>` along with its seed, its map replaces the one of `--map` or `--maze`
+fn scenario_from_args() -> Result<Option<(u64, Scenario)>, String> {
+    let Some(path) = arg_value("--scenario") else {
+        return Ok(None);
+    };
+    if arg_value("--map").is_some() || arg_value("--maze").is_some() {
+        return Err("--scenario declares its own map, drop --map and --maze".to_owned());
+    }
+
+    let path = PathBuf::from(path);
+    let scenario_file = load_scenario_file(&path)?;
+    let map_source = scenario_file
+        .map_source(path.parent().unwrap_or_else(|| Path::new(".")))
+        .map_err(|error| format!("failed to load scenario map {}: {error}", path.display()))?;
+    Ok(Some((
+        scenario_file.seed,
+        Scenario {
+            name: scenario_file.name,
+            map_source,
+            bots: scenario_file.bots,
+            tick_limit: scenario_file.tick_limit,
+            finished: false,
+        },
+    )))
+}
+
+/// Simulation speed picked with `--speed <factor>`, and `--paused` to start paused, which only makes sense with
+/// a window to resume from
+fn time_control_from_args(headless: bool) -> Result<TimeControlPlugin, String> {
+    let time_scale = arg_value("--speed").map_or(Ok(1.), |speed| {
+        speed
+            .parse::<f64>()
+            .ok()
+            .filter(|&speed| (MIN_TIME_SCALE..=MAX_TIME_SCALE).contains(&speed))
+            .ok_or_else(|| {
+                format!("invalid speed: {speed}, expected {MIN_TIME_SCALE} to {MAX_TIME_SCALE}")
+            })
+    })?;
+    let paused = env::args().any(|arg| arg == "--paused");
+    if paused && headless {
+        return Err("--paused needs a window, a headless run would never resume".to_owned());
+    }
+    Ok(TimeControlPlugin { time_scale, paused })
+}
+
+/// Save file restored on startup with `--load <file>`, and quick save file picked with `--save <file>`. Quick saves
+/// only overwrite the loaded save file when it is given explicitly as both.
+fn save_state_from_args() -> SaveStatePlugin {
+    SaveStatePlugin {
+        save_path: arg_value("--save")
+            .map_or_else(|| SaveStatePlugin::default().save_path, PathBuf::from),
+        load_path: arg_value("--load").map(PathBuf::from),
+    }
+}
+
+/// Value following the `name` option on the command line
+fn arg_value(name: &str) -> Option<String> {
+    env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+/// Map file the editor saves to, `--editor-save <file>`. The map loaded with `--map` is only overwritten when given
+/// explicitly.
+fn editor_save_path() -> PathBuf {
+    arg_value("--editor-save").map_or_else(|| EditorPlugin::default().save_path, PathBuf::from)
+}
+
+/// Prints a generated maze as a map file, `maze <algorithm>[:<w>x<h>[:<loop density>]] [--seed <n>]`
+fn print_maze(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let spec = args.next().ok_or("missing maze spec")?;
+    let maze = MazeSettings::from_spec(&spec)?;
+    let seed = match (args.next().as_deref(), args.next()) {
+        (Some("--seed"), Some(seed)) => seed
+            .parse::<u64>()
+            .map_err(|_| format!("invalid seed: {seed}"))?,
+        (None, _) => 0,
+        (Some(arg), _) => return Err(format!("unknown argument: {arg}")),
+    };
+
+    let cells = generate_maze(&maze, &mut WyRand::from_seed(seed.to_le_bytes()));
+    println!("{cells}\n---\n{}", raid_overlay(&cells));
+    Ok(())
+}
+
+/// Prints a map file with its glyph grid rewritten, `convert-map <file> [--to box|hex]`, converts to the
+/// other glyph set by default
+fn print_converted_map(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let path = args.next().ok_or("missing map file")?;
+    let map_string =
+        fs::read_to_string(&path).map_err(|error| format!("failed to read map {path}: {error}"))?;
+    let glyph_set = match (args.next().as_deref(), args.next().as_deref()) {
+        (Some("--to"), Some("box")) => GlyphSet::BoxDrawing,
+        (Some("--to"), Some("hex")) => GlyphSet::HexNibble,
+        (None, _) => match GlyphSet::detect(&parse_map_file(&map_string)?.cells_string) {
+            GlyphSet::BoxDrawing => GlyphSet::HexNibble,
+            GlyphSet::HexNibble => GlyphSet::BoxDrawing,
+        },
+        (Some(arg), _) => return Err(format!("unknown argument: {arg}")),
+    };
+
+    print!("{}", convert_map_file(&map_string, glyph_set)?);
+    Ok(())
+}
+
+/// Simulation plugins shared by every way of running the game, the entropy plugin is added by the caller
+fn add_game_plugins(app: &mut App) {
+    _ = app
+        .add_plugins(PhysicsPlugin)
+        .add_plugins(GameSetupData)
+        .add_plugins(SceneElementsPlugin)
+        .add_plugins(BotsPlugin)
+        .add_plugins(TeamsPlugin)
+        .add_plugins(ItemsPlugin)
+        .add_plugins(DoorsPlugin)
+        .add_plugins(NavPlugin);
+}
+
+fn add_windowed_plugins(app: &mut App) {
+    _ = app
         .add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
@@ -64,18 +337,72 @@ fn main() {
                     ..default()
                 }),
         )
-        .add_plugins(PhysicsPlugin)
         .add_plugins(FrameTimeDiagnosticsPlugin)
-        .add_plugins(LogDiagnosticsPlugin::default())
-        .add_plugins(EntropyPlugin::<WyRand>::default())
-        .add_plugins(GameSetupData)
-        .add_plugins((CameraSetupPlugin, CameraControllerPlugin))
-        .add_plugins(SceneElementsPlugin)
-        .add_plugins(BotsPlugin)
-        .add_plugins((VisualBotsPlugin, VisualSceneElementsPlugin))
-        // .insert_resource(WindowDescriptor {
-        //     title: "CodeRaid".to_string(),
-        //     ..default()
-        // })
-        .run();
+        .add_plugins((
+            CameraSetupPlugin,
+            InputActionsPlugin::default(),
+            CameraControllerPlugin,
+            PickingPlugin,
+        ))
+        .add_plugins((
+            VisualAssetsPlugin,
+            VisualBotsPlugin,
+            VisualBotDebugPlugin,
+            VisualDebugPlugin,
+            VisualHudPlugin,
+            VisualItemsPlugin,
+            VisualMinimapPlugin,
+            VisualSceneElementsPlugin,
+        ))
+        .add_plugins(EditorPlugin {
+            save_path: editor_save_path(),
+        });
+    // .insert_resource(WindowDescriptor {
+    //     title: "CodeRaid".to_string(),
+    //     ..default()
+    // })
+}
+
+/// Runs the simulation as fast as possible without a window or any rendering, e.g. for bot-vs-bot matches
+fn add_headless_plugins(app: &mut App) {
+    _ = app
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)))
+        .add_plugins((TransformPlugin, HierarchyPlugin))
+        // Physics colliders still need the mesh and scene assets to exist
+        .add_plugins((AssetPlugin::default(), ScenePlugin))
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        // Walls colliders are spawned along with the walls, nothing gets rendered without a window
+        .add_plugins((VisualAssetsPlugin, VisualSceneElementsPlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(HEADLESS_UPDATE_STEP));
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::query::With;
+
+    use super::*;
+    use game_bots_plugin::Bot;
+    use game_cells_plugin::Cells;
+
+    #[test]
+    fn test_headless_app_updates() {
+        let mut app = App::new();
+        add_headless_plugins(&mut app);
+        _ = app.add_plugins(EntropyPlugin::<WyRand>::default());
+        add_game_plugins(&mut app);
+
+        // One simulated second, long enough for the first bots to spawn
+        for _ in 0..60 {
+            app.update();
+        }
+
+        assert!(app.world.get_resource::<Cells>().is_some());
+        assert!(app
+            .world
+            .query_filtered::<(), With<Bot>>()
+            .iter(&app.world)
+            .next()
+            .is_some());
+    }
 }
@@ -0,0 +1,223 @@
+use crate::game_cells_plugin::cell;
+
+/// Canonical cell pieces, named after their horizontal openings
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TilePiece {
+    /// Fully closed cell
+    Solid,
+    /// No horizontal opening
+    Enclosed,
+    DeadEnd,
+    Straight,
+    Corner,
+    TJunction,
+    Cross,
+}
+
+impl TilePiece {
+    pub const ALL: [Self; 7] = [
+        Self::Solid,
+        Self::Enclosed,
+        Self::DeadEnd,
+        Self::Straight,
+        Self::Corner,
+        Self::TJunction,
+        Self::Cross,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Solid => "solid",
+            Self::Enclosed => "enclosed",
+            Self::DeadEnd => "dead_end",
+            Self::Straight => "straight",
+            Self::Corner => "corner",
+            Self::TJunction => "t_junction",
+            Self::Cross => "cross",
+        }
+    }
+
+    /// Horizontal openings of the unrotated piece, bits in `HORIZONTAL_FACES` order
+    const fn openings(self) -> u8 {
+        match self {
+            Self::Solid | Self::Enclosed => 0b0000,
+            Self::DeadEnd => 0b0001,
+            Self::Straight => 0b0101,
+            Self::Corner => 0b0011,
+            Self::TJunction => 0b0111,
+            Self::Cross => 0b1111,
+        }
+    }
+}
+
+/// Openings of a cell along Z
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TileVertical {
+    /// Floor and ceiling closed
+    Closed,
+    /// Ceiling open to the level above
+    OpenAbove,
+    /// Floor open to the level below
+    OpenBelow,
+    /// Floor and ceiling open
+    Shaft,
+}
+
+impl TileVertical {
+    pub const ALL: [Self; 4] = [Self::Closed, Self::OpenAbove, Self::OpenBelow, Self::Shaft];
+
+    /// Suffix appended to the piece name, empty for the common closed variant
+    pub const fn suffix(self) -> &'static str {
+        match self {
+            Self::Closed => "",
+            Self::OpenAbove => "_open_above",
+            Self::OpenBelow => "_open_below",
+            Self::Shaft => "_shaft",
+        }
+    }
+}
+
+/// A cell classified as a canonical piece and the rotation placing it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub piece: TilePiece,
+    pub vertical: TileVertical,
+    /// Counterclockwise quarter turns around Z, in `0..4`
+    pub quarter_turns: i32,
+}
+
+impl Tile {
+    /// Model name of the piece and its vertical variant, e.g. `corner_open_above`
+    pub fn model_name(piece: TilePiece, vertical: TileVertical) -> String {
+        format!("{}{}", piece.name(), vertical.suffix())
+    }
+}
+
+/// Horizontal faces in counterclockwise order, a quarter turn around Z moves each to the next one
+const HORIZONTAL_FACES: [cell::Type; 4] = [
+    cell::OPEN_POS_X,
+    cell::OPEN_POS_Y,
+    cell::OPEN_NEG_X,
+    cell::OPEN_NEG_Y,
+];
+
+fn horizontal_openings(cell_type: cell::Type) -> u8 {
+    HORIZONTAL_FACES
+        .iter()
+        .zip(0..)
+        .filter(|&(&face, _)| cell_type.is_open(face))
+        .fold(0_u8, |openings, (_, bit)| openings | 1 << bit)
+}
+
+const fn rotated_openings(openings: u8, quarter_turns: i32) -> u8 {
+    ((openings << quarter_turns) | (openings >> (4 - quarter_turns))) & 0b1111
+}
+
+/// Classifies a cell into its canonical piece, vertical variant and quarter turns. The piece openings
+/// turned by `quarter_turns` are exactly the horizontal openings of the cell.
+pub fn classify_tile(cell_type: cell::Type) -> Tile {
+    if cell_type == cell::EMPTY {
+        return Tile {
+            piece: TilePiece::Solid,
+            vertical: TileVertical::Closed,
+            quarter_turns: 0,
+        };
+    }
+
+    let openings = horizontal_openings(cell_type);
+    let (piece, quarter_turns) = TilePiece::ALL
+        .into_iter()
+        .skip(1)
+        .find_map(|piece| {
+            (0..4)
+                .find(|&quarter_turns| {
+                    rotated_openings(piece.openings(), quarter_turns) == openings
+                })
+                .map(|quarter_turns| (piece, quarter_turns))
+        })
+        .unwrap_or((TilePiece::Enclosed, 0));
+
+    let vertical = match (
+        cell_type.is_open(cell::OPEN_NEG_Z),
+        cell_type.is_open(cell::OPEN_POS_Z),
+    ) {
+        (false, false) => TileVertical::Closed,
+        (false, true) => TileVertical::OpenAbove,
+        (true, false) => TileVertical::OpenBelow,
+        (true, true) => TileVertical::Shaft,
+    };
+
+    Tile {
+        piece,
+        vertical,
+        quarter_turns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_tile_pieces() {
+        let tile = |cell_type| {
+            let Tile {
+                piece,
+                quarter_turns,
+                ..
+            } = classify_tile(cell_type);
+            (piece, quarter_turns)
+        };
+
+        assert_eq!(tile(cell::EMPTY), (TilePiece::Solid, 0));
+        assert_eq!(tile(cell::OPEN_POS_Z), (TilePiece::Enclosed, 0));
+        assert_eq!(tile(cell::OPEN_POS_X), (TilePiece::DeadEnd, 0));
+        assert_eq!(tile(cell::OPEN_NEG_Y), (TilePiece::DeadEnd, 3));
+        assert_eq!(
+            tile(cell::OPEN_NEG_Y | cell::OPEN_POS_Y),
+            (TilePiece::Straight, 1)
+        );
+        assert_eq!(
+            tile(cell::OPEN_NEG_X | cell::OPEN_POS_Y),
+            (TilePiece::Corner, 1)
+        );
+        assert_eq!(
+            tile(cell::OPEN_NEG_X | cell::OPEN_NEG_Y | cell::OPEN_POS_X),
+            (TilePiece::TJunction, 2)
+        );
+        assert_eq!(tile(cell::OPEN_ALL), (TilePiece::Cross, 0));
+    }
+
+    #[test]
+    fn test_classify_tile_verticals() {
+        let vertical = |cell_type| classify_tile(cell::OPEN_POS_X | cell_type).vertical;
+
+        assert_eq!(vertical(cell::OPEN_POS_X), TileVertical::Closed);
+        assert_eq!(vertical(cell::OPEN_POS_Z), TileVertical::OpenAbove);
+        assert_eq!(vertical(cell::OPEN_NEG_Z), TileVertical::OpenBelow);
+        assert_eq!(
+            vertical(cell::OPEN_NEG_Z | cell::OPEN_POS_Z),
+            TileVertical::Shaft
+        );
+        assert_eq!(
+            Tile::model_name(TilePiece::TJunction, TileVertical::Shaft),
+            "t_junction_shaft"
+        );
+    }
+
+    #[test]
+    fn test_classify_tile_rotation_matches_openings() {
+        for nibble in 1..16 {
+            let cell_type = cell::Type::from_xy_nibble(nibble);
+            let tile = classify_tile(cell_type);
+            let openings = horizontal_openings(cell_type);
+
+            assert!((0..4).contains(&tile.quarter_turns));
+            assert_eq!(
+                rotated_openings(tile.piece.openings(), tile.quarter_turns),
+                openings,
+                "{cell_type:?} classified as {tile:?}"
+            );
+        }
+    }
+}
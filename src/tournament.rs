@@ -0,0 +1,563 @@
+use core::fmt::Write as _;
+use core::str::FromStr;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{fs, panic, thread};
+
+use bevy::app::App;
+use bevy::utils::HashMap;
+use bevy_prng::WyRand;
+use bevy_rand::prelude::EntropyPlugin;
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::game_bots_plugin::{BotProgram, TeamPrograms};
+use crate::game_map_file::load_map_file;
+use crate::game_maze_generator::MazeSettings;
+use crate::game_raid_plugin::{RaidMatch, RaidPlugin, RaidResult};
+use crate::game_scene_plugin::MapSource;
+use crate::game_teams_plugin::{Team, TeamScores};
+use crate::{add_game_plugins, add_headless_plugins};
+
+const USAGE: &str = "usage: coderaid tournament [--programs level0,level1,level2,navigator] \
+[--maps <map file>|maze:<algorithm>[:<w>x<h>[:<loop density>]],...] [--format round-robin|swiss] [--rounds <n>] [--seed <n>] \
+[--threads <n>] [--csv <file>] [--json <file>]";
+
+const ATTACKERS: Team = Team::Red;
+const DEFENDERS: Team = Team::Blue;
+
+/// A little longer than the raid match timer at the headless update rate, in case a match never finishes
+const MAX_MATCH_UPDATES: u32 = 150 * 60;
+
+const ELO_INITIAL_RATING: f32 = 1500.;
+const ELO_K_FACTOR: f32 = 32.;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TournamentFormat {
+    /// Every program plays every other program
+    RoundRobin,
+    /// Every round pairs programs with similar standings that have not played each other yet
+    Swiss,
+}
+
+#[derive(Debug)]
+pub struct TournamentConfig {
+    pub programs: Vec<BotProgram>,
+    /// Map name and source, every pairing plays on every map
+    pub maps: Vec<(String, MapSource)>,
+    pub format: TournamentFormat,
+    /// Repetitions of every pairing for round-robin, number of rounds for Swiss
+    pub rounds: usize,
+    /// Base seed, every match gets its own seed derived from it
+    pub seed: u64,
+    pub threads: usize,
+    pub csv_path: Option<PathBuf>,
+    pub json_path: Option<PathBuf>,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            programs: BotProgram::ALL.to_vec(),
+            maps: vec![("default".to_owned(), MapSource::default())],
+            format: TournamentFormat::RoundRobin,
+            rounds: 1,
+            seed: 0,
+            threads: thread::available_parallelism().map_or(1, usize::from),
+            csv_path: None,
+            json_path: None,
+        }
+    }
+}
+
+impl TournamentConfig {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {arg}"))
+            };
+            match arg.as_str() {
+                "--programs" => {
+                    config.programs = value()?
+                        .split(',')
+                        .map(|name| {
+                            BotProgram::from_name(name)
+                                .ok_or_else(|| format!("unknown bot program: {name}"))
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "--maps" => {
+                    config.maps = value()?
+                        .split(',')
+                        .map(load_map)
+                        .collect::<Result<_, _>>()?;
+                }
+                "--format" => {
+                    config.format = match value()?.as_str() {
+                        "round-robin" => TournamentFormat::RoundRobin,
+                        "swiss" => TournamentFormat::Swiss,
+                        format => return Err(format!("unknown tournament format: {format}")),
+                    };
+                }
+                "--rounds" => config.rounds = parse_number(&arg, &value()?)?,
+                "--seed" => config.seed = parse_number(&arg, &value()?)?,
+                "--threads" => config.threads = parse_number(&arg, &value()?)?,
+                "--csv" => config.csv_path = Some(value()?.into()),
+                "--json" => config.json_path = Some(value()?.into()),
+                _ => return Err(format!("unknown argument: {arg}\n{USAGE}")),
+            }
+        }
+
+        if config.programs.iter().unique().count() < 2 {
+            return Err(format!(
+                "a tournament needs at least two bot programs\n{USAGE}"
+            ));
+        }
+        Ok(config)
+    }
+}
+
+/// Loads a map file, or describes a generated maze when prefixed with `maze:`, generated mazes differ per
+/// match seed
+fn load_map(path: &str) -> Result<(String, MapSource), String> {
+    if let Some(maze_spec) = path.strip_prefix("maze:") {
+        let maze = MazeSettings::from_spec(maze_spec)?;
+        return Ok((path.to_owned(), MapSource::from_maze(maze)));
+    }
+
+    Ok((path.to_owned(), load_map_file(path)?))
+}
+
+fn parse_number<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number for {arg}: {value}"))
+}
+
+/// One raid match, the attacking program plays as the attacking team on the given map
+#[derive(Copy, Clone, Debug)]
+struct MatchSpec {
+    attackers: BotProgram,
+    defenders: BotProgram,
+    map_idx: usize,
+    seed: u64,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct MatchOutcome {
+    spec: MatchSpec,
+    /// None when the match did not finish in time, counted as a draw
+    result: Option<RaidResult>,
+    attackers_score: u32,
+    defenders_score: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Standing {
+    program: BotProgram,
+    played: u32,
+    wins: u32,
+    draws: u32,
+    losses: u32,
+    total_score: u32,
+    elo: f32,
+}
+
+impl Standing {
+    const fn new(program: BotProgram) -> Self {
+        Self {
+            program,
+            played: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            total_score: 0,
+            elo: ELO_INITIAL_RATING,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn average_score(&self) -> f32 {
+        if self.played == 0 {
+            0.
+        } else {
+            self.total_score as f32 / self.played as f32
+        }
+    }
+
+    /// Records one match, `points` being 1 for a win, 0.5 for a draw and 0 for a loss
+    fn record(&mut self, points: f32, score: u32) {
+        self.played += 1;
+        self.total_score += score;
+        if points > 0.5 {
+            self.wins += 1;
+        } else if points < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+}
+
+/// A row of the ranking table written to CSV and JSON
+#[derive(Serialize, Debug)]
+struct RankingRow {
+    rank: usize,
+    program: &'static str,
+    played: u32,
+    wins: u32,
+    draws: u32,
+    losses: u32,
+    average_score: f32,
+    elo: f32,
+}
+
+/// Runs the tournament described by the command line arguments and writes the ranking
+pub fn run(args: impl IntoIterator<Item = String>) -> Result<(), String> {
+    let config = TournamentConfig::from_args(args)?;
+
+    let standings = run_tournament(&config);
+    let rows = ranking_rows(standings);
+
+    let csv = ranking_csv(&rows);
+    if let Some(csv_path) = &config.csv_path {
+        fs::write(csv_path, &csv)
+            .map_err(|error| format!("failed to write {}: {error}", csv_path.display()))?;
+    }
+    if let Some(json_path) = &config.json_path {
+        let json = serde_json::to_string_pretty(&rows).map_err(|error| error.to_string())?;
+        fs::write(json_path, json)
+            .map_err(|error| format!("failed to write {}: {error}", json_path.display()))?;
+    }
+    if config.csv_path.is_none() && config.json_path.is_none() {
+        print!("{csv}");
+    }
+
+    Ok(())
+}
+
+fn run_tournament(config: &TournamentConfig) -> Vec<Standing> {
+    let mut standings = config
+        .programs
+        .iter()
+        .copied()
+        .unique()
+        .map(Standing::new)
+        .collect_vec();
+    let mut played_pairs = HashSet::new();
+    let mut next_seed = config.seed;
+
+    let rounds = match config.format {
+        TournamentFormat::RoundRobin => 1,
+        TournamentFormat::Swiss => config.rounds,
+    };
+    for round in 0..rounds {
+        let pairings = match config.format {
+            TournamentFormat::RoundRobin => (0..config.rounds)
+                .flat_map(|_| round_robin_pairings(&config.programs))
+                .collect_vec(),
+            TournamentFormat::Swiss => swiss_pairings(&standings, &played_pairs),
+        };
+        if pairings.is_empty() {
+            break;
+        }
+
+        // Every pairing plays every map from both sides, the maps are rarely symmetric
+        let matches = pairings
+            .iter()
+            .flat_map(|&(first, second)| {
+                (0..config.maps.len())
+                    .flat_map(move |map_idx| [(first, second, map_idx), (second, first, map_idx)])
+            })
+            .map(|(attackers, defenders, map_idx)| {
+                next_seed = next_seed.wrapping_add(1);
+                MatchSpec {
+                    attackers,
+                    defenders,
+                    map_idx,
+                    seed: next_seed,
+                }
+            })
+            .collect_vec();
+
+        eprintln!("Round {}: {} matches", round + 1, matches.len());
+        for outcome in run_matches(&matches, &config.maps, config.threads) {
+            record_outcome(&mut standings, &outcome);
+        }
+        played_pairs.extend(pairings.into_iter().flat_map(|(a, b)| [(a, b), (b, a)]));
+    }
+
+    standings
+}
+
+fn round_robin_pairings(programs: &[BotProgram]) -> Vec<(BotProgram, BotProgram)> {
+    programs
+        .iter()
+        .copied()
+        .unique()
+        .tuple_combinations()
+        .collect_vec()
+}
+
+/// Pairs every program with the next best placed program it has not played yet, falling back to a
+/// rematch when there is none. With an odd number of programs the last one sits the round out.
+fn swiss_pairings(
+    standings: &[Standing],
+    played_pairs: &HashSet<(BotProgram, BotProgram)>,
+) -> Vec<(BotProgram, BotProgram)> {
+    let mut unpaired = sort_standings(standings.to_vec())
+        .into_iter()
+        .map(|standing| standing.program)
+        .collect_vec();
+
+    let mut pairings = vec![];
+    while unpaired.len() >= 2 {
+        let first = unpaired.remove(0);
+        let opponent_idx = unpaired
+            .iter()
+            .position(|&second| !played_pairs.contains(&(first, second)))
+            .unwrap_or(0);
+        let second = unpaired.remove(opponent_idx);
+        pairings.push((first, second));
+    }
+    pairings
+}
+
+/// Runs the matches spread over the worker threads, returning the outcomes in the matches order
+fn run_matches(
+    matches: &[MatchSpec],
+    maps: &[(String, MapSource)],
+    threads: usize,
+) -> Vec<MatchOutcome> {
+    let next_match_idx = AtomicUsize::new(0);
+
+    let mut outcomes = thread::scope(|scope| {
+        let workers = (0..threads.clamp(1, matches.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut outcomes = vec![];
+                    loop {
+                        let match_idx = next_match_idx.fetch_add(1, Ordering::Relaxed);
+                        let Some(spec) = matches.get(match_idx) else {
+                            break;
+                        };
+                        let Some((map_name, map_source)) = maps.get(spec.map_idx) else {
+                            continue;
+                        };
+
+                        let outcome = run_match(*spec, map_source);
+                        eprintln!(
+                            "{} vs {} on {map_name}: {:?} ({} - {})",
+                            spec.attackers.name(),
+                            spec.defenders.name(),
+                            outcome.result,
+                            outcome.attackers_score,
+                            outcome.defenders_score
+                        );
+                        outcomes.push((match_idx, outcome));
+                    }
+                    outcomes
+                })
+            })
+            .collect_vec();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|error| panic::resume_unwind(error))
+            })
+            .collect_vec()
+    });
+
+    outcomes.sort_by_key(|&(match_idx, _)| match_idx);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+/// Plays a single headless raid match as fast as possible
+fn run_match(spec: MatchSpec, map_source: &MapSource) -> MatchOutcome {
+    let mut app = App::new();
+    add_headless_plugins(&mut app);
+    _ = app.add_plugins(EntropyPlugin::<WyRand>::with_seed(spec.seed.to_le_bytes()));
+    add_game_plugins(&mut app);
+    _ = app
+        .insert_resource(map_source.clone())
+        .insert_resource(TeamPrograms {
+            programs: [(ATTACKERS, spec.attackers), (DEFENDERS, spec.defenders)]
+                .into_iter()
+                .collect(),
+        })
+        .add_plugins(RaidPlugin {
+            attackers: ATTACKERS,
+            defenders: DEFENDERS,
+            exit_on_finish: false,
+        });
+
+    // Drive the updates ourselves instead of the schedule runner so the match can stop on its result
+    app.finish();
+    app.cleanup();
+    for _ in 0..MAX_MATCH_UPDATES {
+        app.update();
+        if app.world.resource::<RaidMatch>().result.is_some() {
+            break;
+        }
+    }
+
+    let result = app.world.resource::<RaidMatch>().result;
+    let scores: &HashMap<Team, u32> = &app.world.resource::<TeamScores>().scores;
+    MatchOutcome {
+        spec,
+        result,
+        attackers_score: scores.get(&ATTACKERS).copied().unwrap_or_default(),
+        defenders_score: scores.get(&DEFENDERS).copied().unwrap_or_default(),
+    }
+}
+
+fn record_outcome(standings: &mut [Standing], outcome: &MatchOutcome) {
+    let attackers_points = match outcome.result {
+        Some(RaidResult::AttackersWin) => 1.,
+        Some(RaidResult::DefendersWin) => 0.,
+        None => 0.5,
+    };
+
+    let elo_of = |program| {
+        standings
+            .iter()
+            .find(|standing| standing.program == program)
+            .map(|standing| standing.elo)
+    };
+    let (Some(attackers_elo), Some(defenders_elo)) = (
+        elo_of(outcome.spec.attackers),
+        elo_of(outcome.spec.defenders),
+    ) else {
+        return;
+    };
+    let (attackers_elo, defenders_elo) = elo_update(attackers_elo, defenders_elo, attackers_points);
+
+    for standing in standings {
+        if standing.program == outcome.spec.attackers {
+            standing.record(attackers_points, outcome.attackers_score);
+            standing.elo = attackers_elo;
+        } else if standing.program == outcome.spec.defenders {
+            standing.record(1. - attackers_points, outcome.defenders_score);
+            standing.elo = defenders_elo;
+        }
+    }
+}
+
+/// Returns both players new Elo ratings, `points_a` being what player a scored out of 1
+fn elo_update(rating_a: f32, rating_b: f32, points_a: f32) -> (f32, f32) {
+    let expected_a = 1. / (1. + 10_f32.powf((rating_b - rating_a) / 400.));
+    let delta = ELO_K_FACTOR * (points_a - expected_a);
+    (rating_a + delta, rating_b - delta)
+}
+
+/// Best first: most wins, then highest Elo, then highest average score
+fn sort_standings(mut standings: Vec<Standing>) -> Vec<Standing> {
+    standings.sort_by(|a, b| {
+        b.wins
+            .cmp(&a.wins)
+            .then(b.elo.total_cmp(&a.elo))
+            .then(b.average_score().total_cmp(&a.average_score()))
+    });
+    standings
+}
+
+fn ranking_rows(standings: Vec<Standing>) -> Vec<RankingRow> {
+    sort_standings(standings)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, standing)| RankingRow {
+            rank: idx + 1,
+            program: standing.program.name(),
+            played: standing.played,
+            wins: standing.wins,
+            draws: standing.draws,
+            losses: standing.losses,
+            average_score: standing.average_score(),
+            elo: standing.elo,
+        })
+        .collect()
+}
+
+fn ranking_csv(rows: &[RankingRow]) -> String {
+    let mut csv = "rank,program,played,wins,draws,losses,average_score,elo\n".to_owned();
+    for row in rows {
+        _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{:.2},{:.1}",
+            row.rank,
+            row.program,
+            row.played,
+            row.wins,
+            row.draws,
+            row.losses,
+            row.average_score,
+            row.elo
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elo_update() {
+        let (a, b) = elo_update(1500., 1500., 1.);
+        assert!((a - 1516.).abs() < 1e-3);
+        assert!((b - 1484.).abs() < 1e-3);
+
+        // The favourite barely gains from beating a much weaker player
+        let (a, b) = elo_update(1900., 1500., 1.);
+        assert!(a - 1900. < 3.);
+        assert!((a + b - 3400.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_swiss_pairings_avoid_rematches() {
+        let mut standings = BotProgram::ALL.map(Standing::new).to_vec();
+        for (standing, wins) in standings.iter_mut().zip([3, 2, 1, 0]) {
+            standing.wins = wins;
+        }
+
+        let played_pairs = HashSet::new();
+        assert_eq!(
+            swiss_pairings(&standings, &played_pairs),
+            [
+                (BotProgram::Level0, BotProgram::Level1),
+                (BotProgram::Level2, BotProgram::Navigator)
+            ]
+        );
+
+        let played_pairs = HashSet::from([
+            (BotProgram::Level0, BotProgram::Level1),
+            (BotProgram::Level1, BotProgram::Level0),
+        ]);
+        assert_eq!(
+            swiss_pairings(&standings, &played_pairs),
+            [
+                (BotProgram::Level0, BotProgram::Level2),
+                (BotProgram::Level1, BotProgram::Navigator)
+            ]
+        );
+
+        let odd_standings = standings.into_iter().take(3).collect_vec();
+        assert_eq!(swiss_pairings(&odd_standings, &HashSet::new()).len(), 1);
+    }
+
+    #[test]
+    fn test_round_robin_pairings() {
+        let pairings = round_robin_pairings(&BotProgram::ALL);
+
+        assert_eq!(pairings.len(), 6);
+        assert!(pairings.iter().all(|(a, b)| a != b));
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use itertools::Itertools;
+
+use crate::game_items_plugin::Item;
+use crate::game_teams_plugin::Team;
+use crate::tile_classifier::{Tile, TilePiece, TileVertical};
+
+/// Optional bot body model, authored in the same frame as the primitive body it replaces
+const BOT_MODEL_PATH: &str = "models/bot.glb";
+/// Folder of the optional cell tile models, one `<piece name><vertical suffix>.glb` per tile piece and
+/// vertical variant
+const TILE_MODELS_FOLDER: &str = "models/tiles";
+const ASSETS_FOLDER: &str = "assets";
+
+#[derive(Debug)]
+pub struct VisualAssetsPlugin;
+
+impl Plugin for VisualAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app.init_resource::<VisualAssets>();
+    }
+}
+
+/// Materials of the parts of a bot that show its team
+#[derive(Debug)]
+pub struct TeamMaterials {
+    pub body: Handle<StandardMaterial>,
+    pub light: Handle<StandardMaterial>,
+}
+
+/// Mesh and material handles shared by every spawned bot, item and wall instead of adding new assets for
+/// each of them
+#[derive(Resource, Debug)]
+pub struct VisualAssets {
+    pub bot_body_mesh: Handle<Mesh>,
+    pub indicator_light_mesh: Handle<Mesh>,
+    /// Green and red status lights of the bots
+    pub status_on_material: Handle<StandardMaterial>,
+    pub status_off_material: Handle<StandardMaterial>,
+    pub item_mesh: Handle<Mesh>,
+    pub wall_material: Handle<StandardMaterial>,
+    pub closed_material: Handle<StandardMaterial>,
+    /// Replaces the primitive bot body when the model exists
+    pub bot_scene: Option<Handle<Scene>>,
+    /// Tile models found for the tile pieces, the other pieces keep the primitive walls
+    pub tile_scenes: HashMap<(TilePiece, TileVertical), Handle<Scene>>,
+    teams: HashMap<Team, TeamMaterials>,
+    items: HashMap<Item, Handle<StandardMaterial>>,
+}
+
+impl VisualAssets {
+    pub fn team(&self, team: Team) -> &TeamMaterials {
+        self.teams
+            .get(&team)
+            .expect("Materials are created for every team")
+    }
+
+    pub fn item_material(&self, item: Item) -> &Handle<StandardMaterial> {
+        self.items
+            .get(&item)
+            .expect("Materials are created for every item")
+    }
+}
+
+const fn item_color(item: Item) -> Color {
+    match item {
+        Item::DataChip => Color::rgb(0.1, 0.6, 0.9),
+        Item::Key => Color::rgb(0.9, 0.8, 0.1),
+        Item::Battery => Color::rgb(0.2, 0.9, 0.3),
+    }
+}
+
+/// Loads the first scene of a glTF model if the file exists in the assets folder
+fn load_optional_scene(asset_server: &AssetServer, path: &str) -> Option<Handle<Scene>> {
+    let exists = FileAssetReader::get_base_path()
+        .join(ASSETS_FOLDER)
+        .join(path)
+        .is_file();
+    exists.then(|| {
+        info!("Using model {path}");
+        asset_server.load(format!("{path}#Scene0"))
+    })
+}
+
+impl FromWorld for VisualAssets {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        let bot_scene = load_optional_scene(asset_server, BOT_MODEL_PATH);
+        let tile_scenes = TilePiece::ALL
+            .into_iter()
+            .cartesian_product(TileVertical::ALL)
+            .filter_map(|tile_model| {
+                let path = format!(
+                    "{TILE_MODELS_FOLDER}/{}.glb",
+                    Tile::model_name(tile_model.0, tile_model.1)
+                );
+                load_optional_scene(asset_server, &path).map(|scene| (tile_model, scene))
+            })
+            .collect();
+
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let bot_body_mesh = meshes.add(Cylinder::new(0.25, 0.1));
+        let indicator_light_mesh = meshes.add(Cylinder::new(0.05, 0.05));
+        let item_mesh = meshes.add(Cuboid::from_size(Vec3::splat(0.15)));
+
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let teams = Team::ALL
+            .into_iter()
+            .map(|team| {
+                let team_materials = TeamMaterials {
+                    body: materials.add(team.color()),
+                    light: materials.add(team.light_color()),
+                };
+                (team, team_materials)
+            })
+            .collect();
+        let items = Item::ALL
+            .into_iter()
+            .map(|item| {
+                let material = materials.add(StandardMaterial {
+                    base_color: item_color(item),
+                    emissive: item_color(item),
+                    ..default()
+                });
+                (item, material)
+            })
+            .collect();
+
+        Self {
+            bot_body_mesh,
+            indicator_light_mesh,
+            status_on_material: materials.add(Color::rgb(0.1, 0.9, 0.1)),
+            status_off_material: materials.add(Color::rgb(0.9, 0.1, 0.1)),
+            item_mesh,
+            wall_material: materials.add(Color::rgb(0.9, 0.9, 0.9)),
+            closed_material: materials.add(Color::rgb(0.2, 0.1, 0.0)),
+            bot_scene,
+            tile_scenes,
+            teams,
+            items,
+        }
+    }
+}
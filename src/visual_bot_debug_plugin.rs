@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+
+use crate::game_bots_plugin::{Bot, BotLogicState, BotLogicUpdateTimer};
+use crate::game_coordinates_utils::{CellCoords, CELL_SIZE};
+use crate::game_setup_data::MapData;
+use crate::visual_picking::BotClickedEvent;
+
+/// Radius of the logic update circle right after an update
+const LOGIC_STATE_RADIUS: f32 = 0.3;
+const HEADING_COLOR: Color = Color::CYAN;
+const PROBE_OPEN_COLOR: Color = Color::GREEN;
+const PROBE_CLOSED_COLOR: Color = Color::RED;
+const NEXT_CELL_COLOR: Color = Color::YELLOW;
+const MOVING_COLOR: Color = Color::WHITE;
+const HOLDING_COLOR: Color = Color::ORANGE;
+
+/// Draws what the bots think: heading, probed directions, next cell and logic update state. Clicking a
+/// bot toggles its overlay.
+#[derive(Debug)]
+pub struct VisualBotDebugPlugin;
+
+impl Plugin for VisualBotDebugPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app.add_systems(
+            Update,
+            (bot_debug_picking_system, bot_debug_gizmos_system).chain(),
+        );
+    }
+}
+
+/// Marks the bots whose debug overlay is shown
+#[derive(Component, Debug)]
+pub struct BotDebug;
+
+#[allow(clippy::needless_pass_by_value)]
+fn bot_debug_picking_system(
+    mut commands: Commands,
+    mut bot_clicked_reader: EventReader<BotClickedEvent>,
+    bots_query: Query<Has<BotDebug>, With<Bot>>,
+) {
+    for entity in bot_clicked_reader.read().filter_map(|event| event.entity) {
+        match bots_query.get(entity) {
+            Ok(true) => _ = commands.entity(entity).remove::<BotDebug>(),
+            Ok(false) => _ = commands.entity(entity).insert(BotDebug),
+            Err(_) => {}
+        }
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::type_complexity)]
+fn bot_debug_gizmos_system(
+    map_data: Option<Res<MapData>>,
+    bot_logic_update_timer: Res<BotLogicUpdateTimer>,
+    bots_query: Query<(&Transform, &BotLogicState), (With<Bot>, With<BotDebug>)>,
+    mut gizmos: Gizmos,
+) {
+    let Some(map_data) = map_data else {
+        return;
+    };
+
+    for (transform, logic_state) in &bots_query {
+        let position = transform.translation;
+
+        _ = gizmos.arrow(
+            position,
+            position + *transform.forward() * CELL_SIZE / 2.,
+            HEADING_COLOR,
+        );
+
+        for &(move_direction, open) in &logic_state.probes {
+            let color = if open {
+                PROBE_OPEN_COLOR
+            } else {
+                PROBE_CLOSED_COLOR
+            };
+            gizmos.ray(position, move_direction.as_vec3() * CELL_SIZE * 0.4, color);
+        }
+
+        if let Some(next_cell_indices) = logic_state.next_cell_indices {
+            let next_cell_position =
+                CellCoords::from_cell_indices(next_cell_indices, &map_data.bounds)
+                    .as_game_coordinates();
+            gizmos.rect(
+                next_cell_position,
+                Quat::IDENTITY,
+                Vec2::splat(CELL_SIZE * 0.8),
+                NEXT_CELL_COLOR,
+            );
+        }
+
+        // Shrinks until the next logic update, colored by what the last one decided
+        let state_color = if logic_state.holding {
+            HOLDING_COLOR
+        } else {
+            MOVING_COLOR
+        };
+        let until_update = 1. - bot_logic_update_timer.0.fraction();
+        _ = gizmos.circle(
+            position,
+            Direction3d::Z,
+            LOGIC_STATE_RADIUS * until_update.max(0.1),
+            state_color,
+        );
+    }
+}
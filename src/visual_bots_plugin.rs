@@ -1,103 +1,147 @@
 use bevy::pbr::light_consts::lumens;
 use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
 use bevy::{
     app::{App, Plugin},
-    asset::Assets,
-    ecs::system::{Commands, ResMut},
-    pbr::{PbrBundle, StandardMaterial},
-    render::{color::Color, mesh::Mesh},
+    ecs::system::Commands,
+    pbr::PbrBundle,
+    render::color::Color,
 };
 
 use crate::game_bots_plugin::BotSpawnedEvent;
+use crate::game_teams_plugin::Team;
+use crate::visual_assets_plugin::VisualAssets;
+
+/// Between the two front indicator lights, where the first person camera sits in the bot frame
+pub const BOT_EYE_OFFSET: Vec3 = Vec3::new(0., 0.05, -0.15);
 
 #[derive(Debug)]
 pub struct VisualBotsPlugin;
 
 impl Plugin for VisualBotsPlugin {
     fn build(&self, app: &mut App) {
-        _ = app
-            // .add_systems(Startup, startup_visual_bots)
-            .add_systems(Update, on_bot_spawned_listener_system);
+        _ = app.add_systems(
+            Update,
+            (on_bot_spawned_listener_system, bot_model_tint_system),
+        );
     }
 }
 
-// /// TODO: Preinitialize assets
-// fn startup_visual_bots() {}
+/// Bot model scene, its materials are replaced with the team body material once it is spawned
+#[derive(Component, Debug)]
+struct BotModel(Team);
 
+#[allow(clippy::needless_pass_by_value)]
 fn on_bot_spawned_listener_system(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    visual_assets: Res<VisualAssets>,
     mut bot_spawned_reader: EventReader<BotSpawnedEvent>,
 ) {
     for event in bot_spawned_reader.read() {
+        let team_materials = visual_assets.team(event.team);
         if let Some(mut entity_command) = commands.get_entity(event.entity) {
             _ = entity_command.with_children(|parent| {
-                _ = parent
-                    .spawn(PbrBundle {
-                        mesh: meshes.add(Cylinder::new(0.25, 0.1)),
-                        material: materials.add(Color::rgb(0.6, 0.7, 0.9)),
+                let mut body = if let Some(bot_scene) = &visual_assets.bot_scene {
+                    let mut body = parent.spawn(SpatialBundle::default());
+                    // The model gets its own entity, so tinting it leaves the indicator lights alone
+                    _ = body.with_children(|parent| {
+                        _ = parent.spawn((
+                            SceneBundle {
+                                scene: bot_scene.clone(),
+                                ..default()
+                            },
+                            BotModel(event.team),
+                        ));
+                    });
+                    body
+                } else {
+                    parent.spawn(PbrBundle {
+                        mesh: visual_assets.bot_body_mesh.clone(),
+                        material: team_materials.body.clone(),
                         // transform: Transform::from_xyz(0.0, 0.5, 0.0),
                         // transform: Transform::from_xyz(0.5, 0.5, 0.5),
                         ..default()
                     })
-                    .with_children(|parent| {
-                        _ = parent
-                            .spawn(PbrBundle {
-                                mesh: meshes.add(Cylinder::new(0.05, 0.05)),
-                                material: materials.add(Color::rgb(0.9, 0.9, 0.9)),
-                                transform: Transform::from_xyz(0.0, 0.05, 0.2),
-                                ..default()
-                            })
-                            .with_children(|parent| {
-                                _ = parent.spawn(PointLightBundle {
-                                    point_light: PointLight {
-                                        color: Color::rgb(1.0, 1.0, 1.0),
-                                        intensity: lumens::LUMENS_PER_LED_WATTS * 1.,
-                                        ..default()
-                                    },
-                                    transform: Transform::from_xyz(0., 0.05, 0.),
+                };
+                _ = body.with_children(|parent| {
+                    _ = parent
+                        .spawn(PbrBundle {
+                            mesh: visual_assets.indicator_light_mesh.clone(),
+                            material: team_materials.light.clone(),
+                            transform: Transform::from_xyz(0.0, 0.05, 0.2),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            _ = parent.spawn(PointLightBundle {
+                                point_light: PointLight {
+                                    color: event.team.light_color(),
+                                    intensity: lumens::LUMENS_PER_LED_WATTS * 1.,
                                     ..default()
-                                });
-                            });
-                        _ = parent
-                            .spawn(PbrBundle {
-                                mesh: meshes.add(Cylinder::new(0.05, 0.05)),
-                                material: materials.add(Color::rgb(0.1, 0.9, 0.1)),
-                                transform: Transform::from_xyz(0.2, 0.05, -0.15),
+                                },
+                                transform: Transform::from_xyz(0., 0.05, 0.),
                                 ..default()
-                            })
-                            .with_children(|parent| {
-                                _ = parent.spawn(PointLightBundle {
-                                    point_light: PointLight {
-                                        color: Color::rgb(0.0, 1.0, 0.0),
-                                        intensity: lumens::LUMENS_PER_LED_WATTS * 1.,
-                                        ..default()
-                                    },
-                                    transform: Transform::from_xyz(0., 0.05, 0.),
-                                    ..default()
-                                });
                             });
-                        _ = parent
-                            .spawn(PbrBundle {
-                                mesh: meshes.add(Cylinder::new(0.05, 0.05)),
-                                material: materials.add(Color::rgb(0.9, 0.1, 0.1)),
-                                transform: Transform::from_xyz(-0.2, 0.05, -0.15),
+                        });
+                    _ = parent
+                        .spawn(PbrBundle {
+                            mesh: visual_assets.indicator_light_mesh.clone(),
+                            material: visual_assets.status_on_material.clone(),
+                            transform: Transform::from_xyz(0.2, 0.05, -0.15),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            _ = parent.spawn(PointLightBundle {
+                                point_light: PointLight {
+                                    color: Color::rgb(0.0, 1.0, 0.0),
+                                    intensity: lumens::LUMENS_PER_LED_WATTS * 1.,
+                                    ..default()
+                                },
+                                transform: Transform::from_xyz(0., 0.05, 0.),
                                 ..default()
-                            })
-                            .with_children(|parent| {
-                                _ = parent.spawn(PointLightBundle {
-                                    point_light: PointLight {
-                                        color: Color::rgb(1.0, 0.0, 0.0),
-                                        intensity: lumens::LUMENS_PER_LED_WATTS * 1.,
-                                        ..default()
-                                    },
-                                    transform: Transform::from_xyz(0., 0.05, 0.),
+                            });
+                        });
+                    _ = parent
+                        .spawn(PbrBundle {
+                            mesh: visual_assets.indicator_light_mesh.clone(),
+                            material: visual_assets.status_off_material.clone(),
+                            transform: Transform::from_xyz(-0.2, 0.05, -0.15),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            _ = parent.spawn(PointLightBundle {
+                                point_light: PointLight {
+                                    color: Color::rgb(1.0, 0.0, 0.0),
+                                    intensity: lumens::LUMENS_PER_LED_WATTS * 1.,
                                     ..default()
-                                });
+                                },
+                                transform: Transform::from_xyz(0., 0.05, 0.),
+                                ..default()
                             });
-                    });
+                        });
+                });
             });
         }
     }
 }
+
+/// Colours the loaded bot models by team, the same way the primitive bot bodies are
+#[allow(clippy::needless_pass_by_value)]
+fn bot_model_tint_system(
+    visual_assets: Res<VisualAssets>,
+    mut scene_instance_ready_reader: EventReader<SceneInstanceReady>,
+    bot_model_query: Query<&BotModel>,
+    children_query: Query<&Children>,
+    mut material_query: Query<&mut Handle<StandardMaterial>>,
+) {
+    for event in scene_instance_ready_reader.read() {
+        let Ok(&BotModel(team)) = bot_model_query.get(event.parent) else {
+            continue;
+        };
+        let body_material = &visual_assets.team(team).body;
+        for entity in children_query.iter_descendants(event.parent) {
+            if let Ok(mut material) = material_query.get_mut(entity) {
+                *material = body_material.clone();
+            }
+        }
+    }
+}
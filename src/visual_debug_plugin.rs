@@ -0,0 +1,240 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_xpbd_3d::plugins::debug::{PhysicsDebugPlugin, PhysicsGizmos};
+use itertools::Itertools;
+
+use crate::game_cells_plugin::{cell, Cells};
+use crate::game_coordinates_utils::{CellCoords, CELL_SIZE};
+use crate::game_setup_data::MapData;
+use crate::input_actions_plugin::ButtonAction;
+
+/// Labels are only drawn for the cells closest to the camera, one per cell would not be readable anyway
+const MAX_CELL_LABELS: usize = 128;
+const CELL_LABEL_FONT_SIZE: f32 = 12.;
+
+const GRID_COLOR: Color = Color::GRAY;
+const BOUNDS_COLOR: Color = Color::FUCHSIA;
+const OPEN_FACE_COLOR: Color = Color::GREEN;
+const CLOSED_FACE_COLOR: Color = Color::RED;
+const OPEN_BELOW_COLOR: Color = Color::BLUE;
+const OPEN_ABOVE_COLOR: Color = Color::CYAN;
+
+/// Debug overlays toggled at runtime: collider wireframes, and the cell grid with its coordinates, the map
+/// bounds and the cell openings
+#[derive(Debug)]
+pub struct VisualDebugPlugin;
+
+impl Plugin for VisualDebugPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app
+            .add_plugins(PhysicsDebugPlugin::default())
+            .insert_gizmo_group(
+                PhysicsGizmos::default(),
+                GizmoConfig {
+                    enabled: false,
+                    ..default()
+                },
+            )
+            .init_resource::<DebugOverlays>()
+            .add_systems(
+                Update,
+                (
+                    debug_overlays_toggle_system
+                        .run_if(resource_exists::<ButtonInput<ButtonAction>>),
+                    cell_grid_gizmos_system.run_if(cell_grid_enabled),
+                    cell_labels_system.run_if(cell_grid_enabled),
+                    cell_labels_hide_system.run_if(cell_grid_turned_off),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct DebugOverlays {
+    pub physics: bool,
+    pub cell_grid: bool,
+}
+
+/// Pooled UI text showing the `CellCoords` of a cell
+#[derive(Component, Debug)]
+struct CellLabel;
+
+#[allow(clippy::needless_pass_by_value)]
+fn cell_grid_enabled(debug_overlays: Res<DebugOverlays>) -> bool {
+    debug_overlays.cell_grid
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn cell_grid_turned_off(debug_overlays: Res<DebugOverlays>) -> bool {
+    debug_overlays.is_changed() && !debug_overlays.cell_grid
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn debug_overlays_toggle_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    mut debug_overlays: ResMut<DebugOverlays>,
+    mut config_store: ResMut<GizmoConfigStore>,
+) {
+    if button_actions.just_pressed(ButtonAction::TogglePhysicsDebug) {
+        debug_overlays.physics = !debug_overlays.physics;
+        config_store.config_mut::<PhysicsGizmos>().0.enabled = debug_overlays.physics;
+        info!("Physics debug overlay: {}", debug_overlays.physics);
+    }
+    if button_actions.just_pressed(ButtonAction::ToggleCellGrid) {
+        debug_overlays.cell_grid = !debug_overlays.cell_grid;
+        info!("Cell grid overlay: {}", debug_overlays.cell_grid);
+    }
+}
+
+/// Outlines the floor of every cell, the map bounds, and colors every face by whether it is open
+#[allow(clippy::needless_pass_by_value)]
+fn cell_grid_gizmos_system(
+    cells: Option<Res<Cells>>,
+    map_data: Option<Res<MapData>>,
+    mut gizmos: Gizmos,
+) {
+    let (Some(cells), Some(map_data)) = (cells, map_data) else {
+        return;
+    };
+    let half_cell = CELL_SIZE / 2.;
+
+    let bounds_min = CellCoords::from_ivec3(map_data.bounds.min).as_game_coordinates();
+    let bounds_size = map_data.bounds.size().as_vec3() * CELL_SIZE;
+    gizmos.cuboid(
+        Transform::from_translation(bounds_min - half_cell + bounds_size / 2.)
+            .with_scale(bounds_size),
+        BOUNDS_COLOR,
+    );
+
+    for cell_indices in cells.indices() {
+        let Some(&cell_type) = cells.get(cell_indices) else {
+            continue;
+        };
+        let center =
+            CellCoords::from_cell_indices(cell_indices, &map_data.bounds).as_game_coordinates();
+        let floor_center = center - Vec3::Z * half_cell;
+
+        gizmos.rect(
+            floor_center,
+            Quat::IDENTITY,
+            Vec2::splat(CELL_SIZE),
+            GRID_COLOR,
+        );
+
+        // Slightly inside the cell so both sides of a face stay visible
+        for (face, direction) in [
+            (cell::OPEN_NEG_X, Vec3::NEG_X),
+            (cell::OPEN_POS_X, Vec3::X),
+            (cell::OPEN_NEG_Y, Vec3::NEG_Y),
+            (cell::OPEN_POS_Y, Vec3::Y),
+        ] {
+            let color = if cell_type.is_open(face) {
+                OPEN_FACE_COLOR
+            } else {
+                CLOSED_FACE_COLOR
+            };
+            let face_center = floor_center + direction * half_cell * 0.8;
+            let along = direction.cross(Vec3::Z) * half_cell * 0.6;
+            gizmos.line(face_center - along, face_center + along, color);
+        }
+
+        for (face, color, radius) in [
+            (cell::OPEN_NEG_Z, OPEN_BELOW_COLOR, 0.2),
+            (cell::OPEN_POS_Z, OPEN_ABOVE_COLOR, 0.25),
+        ] {
+            if cell_type.is_open(face) {
+                _ = gizmos.circle(floor_center, Direction3d::Z, radius * CELL_SIZE, color);
+            }
+        }
+    }
+}
+
+/// Places the cell coordinates labels over the cells closest to the camera, hiding the unused ones
+#[allow(clippy::needless_pass_by_value)]
+fn cell_labels_system(
+    mut commands: Commands,
+    cells: Option<Res<Cells>>,
+    map_data: Option<Res<MapData>>,
+    windows_query: Query<&Window, With<PrimaryWindow>>,
+    cameras_query: Query<(&Camera, &GlobalTransform)>,
+    mut labels_query: Query<(&mut Text, &mut Style, &mut Visibility), With<CellLabel>>,
+) {
+    let shown_labels = match (cells, map_data, windows_query.get_single()) {
+        (Some(cells), Some(map_data), Ok(window)) => cameras_query
+            .iter()
+            .find(|(camera, _)| camera.is_active)
+            .map(|(camera, camera_transform)| {
+                cells
+                    .indices()
+                    .filter_map(|cell_indices| {
+                        let cell_coords =
+                            CellCoords::from_cell_indices(cell_indices, &map_data.bounds);
+                        let position = cell_coords.as_game_coordinates();
+                        let viewport_position =
+                            camera.world_to_viewport(camera_transform, position)?;
+                        let on_screen = viewport_position.cmpge(Vec2::ZERO).all()
+                            && viewport_position
+                                .cmplt(Vec2::new(window.width(), window.height()))
+                                .all();
+                        let distance = camera_transform.translation().distance_squared(position);
+                        on_screen.then_some((cell_coords, viewport_position, distance))
+                    })
+                    .sorted_unstable_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+                    .take(MAX_CELL_LABELS)
+                    .collect_vec()
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    let mut labels = labels_query.iter_mut();
+    for &(cell_coords, viewport_position, _) in &shown_labels {
+        let label = cell_coords.to_string();
+        if let Some((mut text, mut style, mut visibility)) = labels.next() {
+            if text
+                .sections
+                .first()
+                .is_some_and(|section| section.value != label)
+            {
+                if let Some(section) = text.sections.first_mut() {
+                    section.value = label;
+                }
+            }
+            let moved_style = Style {
+                left: Val::Px(viewport_position.x),
+                top: Val::Px(viewport_position.y),
+                ..style.clone()
+            };
+            _ = style.set_if_neq(moved_style);
+            _ = visibility.set_if_neq(Visibility::Inherited);
+        } else {
+            _ = commands.spawn((
+                TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font_size: CELL_LABEL_FONT_SIZE,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(viewport_position.x),
+                    top: Val::Px(viewport_position.y),
+                    ..default()
+                }),
+                CellLabel,
+            ));
+        }
+    }
+    for (_, _, mut visibility) in labels {
+        _ = visibility.set_if_neq(Visibility::Hidden);
+    }
+}
+
+/// Hides all the cell labels once when the cell grid is turned off
+fn cell_labels_hide_system(mut labels_query: Query<&mut Visibility, With<CellLabel>>) {
+    for mut visibility in &mut labels_query {
+        _ = visibility.set_if_neq(Visibility::Hidden);
+    }
+}
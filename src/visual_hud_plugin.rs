@@ -0,0 +1,324 @@
+use core::fmt::Write;
+
+use bevy::diagnostic::{Diagnostic, DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::ecs::query::QueryFilter;
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use itertools::Itertools;
+
+use crate::game_bots_plugin::{Bot, BotLogicState, BotProgram, BotSpawner};
+use crate::game_coordinates_utils::CellCoords;
+use crate::game_items_plugin::Inventory;
+use crate::game_scene_plugin::SkipStartupSpawn;
+use crate::game_setup_data::MapData;
+use crate::game_teams_plugin::{SpawnPoints, Team};
+use crate::game_time_plugin::SimulationTick;
+use crate::input_actions_plugin::ButtonAction;
+use crate::visual_picking::SelectedBot;
+
+const HUD_FONT_SIZE: f32 = 14.;
+const HUD_MARGIN: f32 = 8.;
+const HUD_BACKGROUND_COLOR: Color = Color::rgba(0., 0., 0., 0.6);
+/// Bots listed in the stats panel, the others are only counted
+const MAX_LISTED_BOTS: usize = 16;
+
+/// Live simulation stats in the top left corner, and an inspector of the selected bot in the top right corner
+#[derive(Debug)]
+pub struct VisualHudPlugin;
+
+impl Plugin for VisualHudPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app
+            .init_resource::<Hud>()
+            .add_systems(Startup, setup_hud)
+            .add_systems(
+                Update,
+                (hud_toggle_system, hud_stats_system, hud_inspector_system).chain(),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct Hud {
+    pub shown: bool,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Self { shown: true }
+    }
+}
+
+#[derive(Component, Debug)]
+struct StatsPanel;
+
+#[derive(Component, Debug)]
+struct StatsText;
+
+#[derive(Component, Debug)]
+struct InspectorPanel;
+
+#[derive(Component, Debug)]
+struct InspectorText;
+
+fn setup_hud(mut commands: Commands) {
+    let panel_bundle = |left, right| NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left,
+            right,
+            top: Val::Px(HUD_MARGIN),
+            padding: UiRect::all(Val::Px(HUD_MARGIN)),
+            ..default()
+        },
+        background_color: HUD_BACKGROUND_COLOR.into(),
+        ..default()
+    };
+    let text_bundle = || {
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: HUD_FONT_SIZE,
+                ..default()
+            },
+        )
+    };
+
+    _ = commands
+        .spawn((panel_bundle(Val::Px(HUD_MARGIN), Val::Auto), StatsPanel))
+        .with_children(|parent| {
+            _ = parent.spawn((text_bundle(), StatsText));
+        });
+    _ = commands
+        .spawn((panel_bundle(Val::Auto, Val::Px(HUD_MARGIN)), InspectorPanel))
+        .with_children(|parent| {
+            _ = parent.spawn((text_bundle(), InspectorText));
+        });
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn hud_toggle_system(button_actions: Res<ButtonInput<ButtonAction>>, mut hud: ResMut<Hud>) {
+    if button_actions.just_pressed(ButtonAction::ToggleHud) {
+        hud.shown = !hud.shown;
+    }
+}
+
+/// Frame rate, simulation progress, bot spawning and a line per bot
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+fn hud_stats_system(
+    hud: Res<Hud>,
+    diagnostics: Res<DiagnosticsStore>,
+    simulation_tick: Res<SimulationTick>,
+    time: Res<Time>,
+    virtual_time: Res<Time<Virtual>>,
+    bot_spawner: Res<BotSpawner>,
+    spawn_points: Option<Res<SpawnPoints>>,
+    skip_startup_spawn: Option<Res<SkipStartupSpawn>>,
+    bots_query: Query<(Entity, &Team, &Transform, &LinearVelocity, &Mass), With<Bot>>,
+    mut panel_query: Query<&mut Visibility, With<StatsPanel>>,
+    mut text_query: Query<&mut Text, With<StatsText>>,
+) {
+    for mut visibility in &mut panel_query {
+        _ = visibility.set_if_neq(if hud.shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+    if !hud.shown {
+        return;
+    }
+
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(Diagnostic::smoothed)
+        .unwrap_or_default();
+    let bots = bots_query
+        .iter()
+        .sorted_by_key(|&(entity, ..)| entity)
+        .collect_vec();
+    let team_counts = bots.iter().counts_by(|&(_, &team, ..)| team);
+    // Maps without spawn points get a single bot
+    let spawn_points_count = spawn_points
+        .map(|spawn_points| spawn_points.spawn_points.len())
+        .filter(|&count| count > 0)
+        .unwrap_or(1);
+
+    let mut stats = format!(
+        "FPS {fps:.1}\nTick {}  Time {:.1}s  {}\nBots {}",
+        simulation_tick.0,
+        time.elapsed_seconds(),
+        time_status(virtual_time.is_paused(), virtual_time.relative_speed_f64()),
+        bots.len()
+    );
+    for team in [Team::Red, Team::Blue] {
+        _ = write!(
+            stats,
+            "  {team:?} {}",
+            team_counts.get(&team).copied().unwrap_or_default()
+        );
+    }
+    _ = write!(
+        stats,
+        "\n{}\n\nBot     Team  Cell          Speed  Energy",
+        spawner_status(
+            skip_startup_spawn.is_some(),
+            bot_spawner.spawned,
+            spawn_points_count,
+            bot_spawner.timer.remaining_secs()
+        )
+    );
+    for &(entity, team, transform, linear_velocity, mass) in bots.iter().take(MAX_LISTED_BOTS) {
+        _ = write!(
+            stats,
+            "\n{:<7} {:<5} {:<13} {:<6.2} {:.3}",
+            format!("{entity:?}"),
+            format!("{team:?}"),
+            CellCoords::from_game_coordinates(transform.translation.round()).to_string(),
+            linear_velocity.length(),
+            kinetic_energy(mass.0, linear_velocity.0)
+        );
+    }
+    if bots.len() > MAX_LISTED_BOTS {
+        _ = write!(stats, "\n... {} more", bots.len() - MAX_LISTED_BOTS);
+    }
+
+    set_text(&mut text_query, &stats);
+}
+
+/// Everything known about the selected bot, hidden when no bot is selected
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::type_complexity)]
+fn hud_inspector_system(
+    hud: Res<Hud>,
+    selected_bot: Res<SelectedBot>,
+    map_data: Option<Res<MapData>>,
+    bots_query: Query<
+        (
+            &Team,
+            &BotProgram,
+            &Transform,
+            &LinearVelocity,
+            &Mass,
+            &BotLogicState,
+            &Inventory,
+        ),
+        With<Bot>,
+    >,
+    mut panel_query: Query<&mut Visibility, With<InspectorPanel>>,
+    mut text_query: Query<&mut Text, With<InspectorText>>,
+) {
+    let inspected = selected_bot
+        .0
+        .filter(|_| hud.shown)
+        .and_then(|entity| Some((entity, bots_query.get(entity).ok()?)));
+    for mut visibility in &mut panel_query {
+        _ = visibility.set_if_neq(if inspected.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+    let Some((entity, (team, program, transform, linear_velocity, mass, logic_state, inventory))) =
+        inspected
+    else {
+        return;
+    };
+
+    let next_cell = match (logic_state.next_cell_indices, &map_data) {
+        (Some(cell_indices), Some(map_data)) => {
+            CellCoords::from_cell_indices(cell_indices, &map_data.bounds).to_string()
+        }
+        _ => "-".to_owned(),
+    };
+    let items = if inventory.items.is_empty() {
+        "-".to_owned()
+    } else {
+        inventory
+            .items
+            .iter()
+            .map(|item| format!("{item:?}"))
+            .join(", ")
+    };
+    let inspector = format!(
+        "Bot {entity:?}\nTeam {team:?}  Program {}\nCell {}\nVelocity ({:.2}, {:.2}, {:.2})  Speed {:.2}\n\
+         Energy {:.3}\nLogic ticks {}  Next cell {next_cell}  Holding {}\nItems {items}",
+        program.name(),
+        CellCoords::from_game_coordinates(transform.translation.round()),
+        linear_velocity.x,
+        linear_velocity.y,
+        linear_velocity.z,
+        linear_velocity.length(),
+        kinetic_energy(mass.0, linear_velocity.0),
+        logic_state.ticks,
+        if logic_state.holding { "yes" } else { "no" },
+    );
+
+    set_text(&mut text_query, &inspector);
+}
+
+fn set_text<F: QueryFilter>(text_query: &mut Query<&mut Text, F>, value: &str) {
+    for mut text in text_query.iter_mut() {
+        if let Some(section) = text.sections.first_mut() {
+            value.clone_into(&mut section.value);
+        }
+    }
+}
+
+fn kinetic_energy(mass: f32, velocity: Vec3) -> f32 {
+    0.5 * mass * velocity.length_squared()
+}
+
+fn time_status(paused: bool, time_scale: f64) -> String {
+    if paused {
+        "Paused".to_owned()
+    } else {
+        format!("x{time_scale}")
+    }
+}
+
+/// Scenarios spawn their own bots, the spawner never runs for them
+fn spawner_status(
+    skipped: bool,
+    spawned: usize,
+    spawn_points_count: usize,
+    remaining_secs: f32,
+) -> String {
+    if skipped {
+        "Spawner off (scenario)".to_owned()
+    } else if spawned >= spawn_points_count {
+        format!("Spawner done, {spawned} spawned")
+    } else {
+        format!("Spawner {spawned}/{spawn_points_count}, next in {remaining_secs:.1}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kinetic_energy() {
+        assert!((kinetic_energy(2., Vec3::new(3., 4., 0.)) - 25.).abs() < 1e-4);
+        assert!(kinetic_energy(2., Vec3::ZERO).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_spawner_status() {
+        assert_eq!(
+            spawner_status(false, 1, 4, 0.3),
+            "Spawner 1/4, next in 0.3s"
+        );
+        assert_eq!(spawner_status(false, 4, 4, 0.25), "Spawner done, 4 spawned");
+        assert_eq!(spawner_status(true, 0, 4, 0.5), "Spawner off (scenario)");
+    }
+
+    #[test]
+    fn test_time_status() {
+        assert_eq!(time_status(true, 2.), "Paused");
+        assert_eq!(time_status(false, 0.25), "x0.25");
+        assert_eq!(time_status(false, 16.), "x16");
+    }
+}
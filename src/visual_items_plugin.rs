@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+use bevy::{
+    app::{App, Plugin},
+    ecs::system::Commands,
+    pbr::PbrBundle,
+};
+
+use crate::game_items_plugin::ItemSpawnedEvent;
+use crate::visual_assets_plugin::VisualAssets;
+
+#[derive(Debug)]
+pub struct VisualItemsPlugin;
+
+impl Plugin for VisualItemsPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app.add_systems(Update, on_item_spawned_listener_system);
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn on_item_spawned_listener_system(
+    mut commands: Commands,
+    visual_assets: Res<VisualAssets>,
+    mut item_spawned_reader: EventReader<ItemSpawnedEvent>,
+) {
+    for event in item_spawned_reader.read() {
+        if let Some(mut entity_command) = commands.get_entity(event.entity) {
+            _ = entity_command.with_children(|parent| {
+                _ = parent.spawn(PbrBundle {
+                    mesh: visual_assets.item_mesh.clone(),
+                    material: visual_assets.item_material(event.item).clone(),
+                    ..default()
+                });
+            });
+        }
+    }
+}
@@ -0,0 +1,529 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use bevy::ui::{FocusPolicy, RelativeCursorPosition};
+use bevy::utils::HashSet;
+
+use crate::camera_controller_plugin::{target_level, CameraTarget};
+use crate::game_bots_plugin::Bot;
+use crate::game_cells_plugin::{cell, Cells};
+use crate::game_coordinates_utils::{CellCoords, CELL_SIZE};
+use crate::game_scene_plugin::{CellsChangedEvent, CellsSpawnedEvent};
+use crate::game_setup_data::MapData;
+use crate::game_teams_plugin::Team;
+use crate::ibounds3::IBounds3;
+use crate::input_actions_plugin::ButtonAction;
+use crate::visual_picking::SelectedBot;
+
+/// Side of the square of pixels a cell is drawn with, its outer pixels being its walls
+const MINIMAP_CELL_PIXELS: i32 = 4;
+/// Longest side of the minimap on screen, in logical pixels
+const MINIMAP_MAX_SIZE: f32 = 256.;
+const MINIMAP_MARGIN: f32 = 8.;
+
+const SOLID_COLOR: Color = Color::rgb(0.1, 0.1, 0.12);
+const WALL_COLOR: Color = Color::rgb(0.3, 0.3, 0.35);
+const FLOOR_COLOR: Color = Color::rgb(0.7, 0.7, 0.75);
+const OPEN_BELOW_COLOR: Color = Color::BLUE;
+const OPEN_ABOVE_COLOR: Color = Color::CYAN;
+const FOOTPRINT_COLOR: Color = Color::YELLOW;
+/// Side of the square a bot is drawn with, in minimap pixels
+const BOT_MARKER_PIXELS: f32 = 2.;
+
+/// Minimap in the bottom right corner: the level of the camera target drawn from the cells, the bots on it
+/// and what the camera sees of it. Clicking it moves the camera target there.
+#[derive(Debug)]
+pub struct VisualMinimapPlugin;
+
+impl Plugin for VisualMinimapPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app.add_systems(Startup, setup_minimap).add_systems(
+            Update,
+            (
+                minimap_click_system,
+                minimap_system,
+                minimap_footprint_system,
+                minimap_bots_system,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Resource, Debug)]
+struct Minimap {
+    /// Cells of the drawn level, only redrawn when they or the level change
+    image: Handle<Image>,
+    /// Camera footprint drawn over the cells, only redrawn when the camera moves
+    footprint_image: Handle<Image>,
+    /// Cell indices level drawn
+    level: Option<i32>,
+    /// Size in pixels
+    size: IVec2,
+}
+
+#[derive(Component, Debug)]
+struct MinimapNode;
+
+/// Marks the minimap node following a bot on its level
+#[derive(Component, Debug)]
+struct MinimapBotMarker {
+    bot: Entity,
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn setup_minimap(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(minimap_image(IVec2::ONE, &[SOLID_COLOR.as_rgba_u8()]));
+    let footprint_image = images.add(minimap_image(IVec2::ONE, &[[0; 4]]));
+
+    _ = commands
+        .spawn((
+            ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(MINIMAP_MARGIN),
+                    bottom: Val::Px(MINIMAP_MARGIN),
+                    ..default()
+                },
+                image: UiImage::new(image.clone()),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            // Makes the minimap cover the world under it for the cursor ray
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+            MinimapNode,
+        ))
+        .with_children(|parent| {
+            _ = parent.spawn(ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                image: UiImage::new(footprint_image.clone()),
+                focus_policy: FocusPolicy::Pass,
+                ..default()
+            });
+        });
+    commands.insert_resource(Minimap {
+        image,
+        footprint_image,
+        level: None,
+        size: IVec2::ZERO,
+    });
+}
+
+/// Redraws the cells of the minimap when they or the drawn level changed
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::cast_precision_loss)]
+fn minimap_system(
+    cells: Option<Res<Cells>>,
+    map_data: Option<Res<MapData>>,
+    mut minimap: ResMut<Minimap>,
+    mut images: ResMut<Assets<Image>>,
+    mut cells_spawned_reader: EventReader<CellsSpawnedEvent>,
+    mut cells_changed_reader: EventReader<CellsChangedEvent>,
+    cameras_query: Query<(&Camera, &CameraTarget)>,
+    mut node_query: Query<(&mut Style, &mut Visibility), With<MinimapNode>>,
+) {
+    let cells_updated =
+        cells_spawned_reader.read().count() + cells_changed_reader.read().count() > 0;
+    let (Some(cells), Some(map_data)) = (cells, map_data) else {
+        return;
+    };
+    let Some((_, camera_target)) = cameras_query.iter().find(|(camera, _)| camera.is_active) else {
+        return;
+    };
+    let bounds = &map_data.bounds;
+    let size = cells.size.truncate() * MINIMAP_CELL_PIXELS;
+    if size.cmple(IVec2::ZERO).any() {
+        return;
+    }
+
+    let level = target_level(camera_target.target, bounds) - bounds.min.z;
+    if !cells_updated && minimap.level == Some(level) && minimap.size == size {
+        return;
+    }
+
+    if let Some(image) = images.get_mut(&minimap.image) {
+        *image = minimap_image(size, &level_pixels(&cells, level));
+    }
+    if minimap.size != size {
+        if let Some(image) = images.get_mut(&minimap.footprint_image) {
+            *image = minimap_image(size, &vec![[0; 4]; pixels_count(size)]);
+        }
+    }
+    minimap.level = Some(level);
+    minimap.size = size;
+
+    let scale = MINIMAP_MAX_SIZE / size.max_element() as f32;
+    for (mut style, mut visibility) in &mut node_query {
+        style.width = Val::Px(size.x as f32 * scale);
+        style.height = Val::Px(size.y as f32 * scale);
+        *visibility = Visibility::Inherited;
+    }
+}
+
+/// Redraws what the camera sees of the plane through the middle of the drawn level when it moved
+#[allow(clippy::needless_pass_by_value)]
+fn minimap_footprint_system(
+    map_data: Option<Res<MapData>>,
+    minimap: Res<Minimap>,
+    mut images: ResMut<Assets<Image>>,
+    cameras_query: Query<(&Camera, &GlobalTransform)>,
+    mut drawn_footprint: Local<Option<[Option<Vec2>; 4]>>,
+) {
+    let (Some(map_data), Some(level)) = (map_data, minimap.level) else {
+        return;
+    };
+    let Some((camera, camera_transform)) =
+        cameras_query.iter().find(|(camera, _)| camera.is_active)
+    else {
+        return;
+    };
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return;
+    };
+    let bounds = &map_data.bounds;
+
+    let level_center =
+        CellCoords::from_cell_indices(IVec3::new(0, 0, level), bounds).as_game_coordinates();
+    let footprint = [
+        Vec2::ZERO,
+        Vec2::new(viewport_size.x, 0.),
+        viewport_size,
+        Vec2::new(0., viewport_size.y),
+    ]
+    .map(|viewport_position| {
+        let ray = camera.viewport_to_world(camera_transform, viewport_position)?;
+        let distance = ray.intersect_plane(level_center, Plane3d::new(Vec3::Z))?;
+        Some(minimap_pixel(ray.get_point(distance), bounds))
+    });
+    if !minimap.is_changed() && *drawn_footprint == Some(footprint) {
+        return;
+    }
+    *drawn_footprint = Some(footprint);
+
+    let mut pixels = vec![[0; 4]; pixels_count(minimap.size)];
+    for (idx, &corner) in footprint.iter().enumerate() {
+        let next_corner = footprint
+            .get((idx + 1) % footprint.len())
+            .copied()
+            .flatten();
+        if let (Some(from), Some(to)) = (corner, next_corner) {
+            draw_line(&mut pixels, minimap.size, from, to, FOOTPRINT_COLOR);
+        }
+    }
+    if let Some(image) = images.get_mut(&minimap.footprint_image) {
+        image.data = pixels.concat();
+    }
+}
+
+/// Keeps one marker node per bot over the minimap, moved only when its bot moved or the drawn level changed
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::type_complexity)]
+#[allow(clippy::cast_precision_loss)]
+fn minimap_bots_system(
+    mut commands: Commands,
+    map_data: Option<Res<MapData>>,
+    minimap: Res<Minimap>,
+    node_query: Query<Entity, With<MinimapNode>>,
+    bots_query: Query<(Entity, Ref<Transform>, &Team), With<Bot>>,
+    mut markers_query: Query<(Entity, Ref<MinimapBotMarker>, &mut Style, &mut Visibility)>,
+) {
+    let (Some(map_data), Some(level)) = (map_data, minimap.level) else {
+        return;
+    };
+    let Ok(node) = node_query.get_single() else {
+        return;
+    };
+    let bounds = &map_data.bounds;
+    let size = minimap.size.as_vec2();
+
+    let mut marked_bots = HashSet::new();
+    for (marker, bot_marker, mut style, mut visibility) in &mut markers_query {
+        let Ok((_, transform, _)) = bots_query.get(bot_marker.bot) else {
+            commands.entity(marker).despawn_recursive();
+            continue;
+        };
+        _ = marked_bots.insert(bot_marker.bot);
+        if !transform.is_changed() && !bot_marker.is_added() && !minimap.is_changed() {
+            continue;
+        }
+
+        let bot_level = CellCoords::from_game_coordinates(transform.translation.round())
+            .as_ivec3()
+            .z
+            - bounds.min.z;
+        *visibility = if bot_level == level {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        let top_left =
+            (minimap_pixel(transform.translation, bounds) - BOT_MARKER_PIXELS / 2.) / size;
+        style.left = Val::Percent(top_left.x * 100.);
+        style.top = Val::Percent(top_left.y * 100.);
+        style.width = Val::Percent(BOT_MARKER_PIXELS / size.x * 100.);
+        style.height = Val::Percent(BOT_MARKER_PIXELS / size.y * 100.);
+    }
+
+    for (bot, _, team) in &bots_query {
+        if !marked_bots.contains(&bot) {
+            // Placed by the next update, as a new marker
+            let marker = commands
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        },
+                        background_color: team.light_color().into(),
+                        focus_policy: FocusPolicy::Pass,
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    MinimapBotMarker { bot },
+                ))
+                .id();
+            _ = commands.entity(node).add_child(marker);
+        }
+    }
+}
+
+/// Moves the camera target to the clicked point of the minimap, on the same level
+#[allow(clippy::needless_pass_by_value)]
+fn minimap_click_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    map_data: Option<Res<MapData>>,
+    minimap: Res<Minimap>,
+    node_query: Query<&RelativeCursorPosition, With<MinimapNode>>,
+    mut selected_bot: ResMut<SelectedBot>,
+    mut cameras_query: Query<&mut CameraTarget>,
+) {
+    let Some(map_data) = map_data else {
+        return;
+    };
+    if !button_actions.just_pressed(ButtonAction::Select) {
+        return;
+    }
+    let Some(normalized) = node_query
+        .iter()
+        .find(|relative_cursor_position| relative_cursor_position.mouse_over())
+        .and_then(|relative_cursor_position| relative_cursor_position.normalized)
+    else {
+        return;
+    };
+
+    // Jumping takes over from following the selected bot
+    selected_bot.0 = None;
+    let point = minimap_point(normalized * minimap.size.as_vec2(), &map_data.bounds);
+    for mut camera_target in &mut cameras_query {
+        camera_target.target = point.extend(camera_target.target.z);
+    }
+}
+
+#[allow(clippy::cast_sign_loss)]
+const fn pixels_count(size: IVec2) -> usize {
+    (size.x * size.y) as usize
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn minimap_image(size: IVec2, pixels: &[[u8; 4]]) -> Image {
+    let mut image = Image::new(
+        Extent3d {
+            width: size.x as u32,
+            height: size.y as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixels.concat(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+/// Pixels of a level of the cells, row by row from the top, `MINIMAP_CELL_PIXELS` square pixels per cell
+fn level_pixels(cells: &Cells, level: i32) -> Vec<[u8; 4]> {
+    let last = MINIMAP_CELL_PIXELS - 1;
+    let size = cells.size.truncate() * MINIMAP_CELL_PIXELS;
+
+    (0..size.y)
+        .flat_map(|row| (0..size.x).map(move |column| IVec2::new(column, row)))
+        .map(|pixel| {
+            let cell_indices = IVec3::new(
+                pixel.x / MINIMAP_CELL_PIXELS,
+                cells.size.y - 1 - pixel.y / MINIMAP_CELL_PIXELS,
+                level,
+            );
+            let cell_type = cells.get(cell_indices).copied().unwrap_or(cell::EMPTY);
+            // Position inside the cell square, rows going down like the image rows
+            let block = pixel % MINIMAP_CELL_PIXELS;
+            let sides = [
+                (cell::OPEN_NEG_X, block.x == 0),
+                (cell::OPEN_POS_X, block.x == last),
+                (cell::OPEN_POS_Y, block.y == 0),
+                (cell::OPEN_NEG_Y, block.y == last),
+            ];
+            let on_corner = (block.x == 0 || block.x == last) && (block.y == 0 || block.y == last);
+
+            let color = if cell_type == cell::EMPTY {
+                SOLID_COLOR
+            } else if on_corner
+                || sides
+                    .into_iter()
+                    .any(|(face, on_side)| on_side && !cell_type.is_open(face))
+            {
+                WALL_COLOR
+            } else if block.x == block.y && cell_type.is_open(cell::OPEN_NEG_Z) {
+                OPEN_BELOW_COLOR
+            } else if block.x + block.y == last && cell_type.is_open(cell::OPEN_POS_Z) {
+                OPEN_ABOVE_COLOR
+            } else {
+                FLOOR_COLOR
+            };
+            color.as_rgba_u8()
+        })
+        .collect()
+}
+
+/// Position in minimap pixels from the top left corner of a point in game coordinates
+#[allow(clippy::cast_precision_loss)]
+fn minimap_pixel(point: Vec3, bounds: &IBounds3) -> Vec2 {
+    let min = CellCoords::from_ivec3(bounds.min).as_game_coordinates() - CELL_SIZE / 2.;
+    let size = bounds.size().as_vec3() * CELL_SIZE;
+
+    Vec2::new(point.x - min.x, min.y + size.y - point.y) * (MINIMAP_CELL_PIXELS as f32 / CELL_SIZE)
+}
+
+/// Game coordinates on the XY plane of a position in minimap pixels, the inverse of `minimap_pixel`
+#[allow(clippy::cast_precision_loss)]
+fn minimap_point(pixel: Vec2, bounds: &IBounds3) -> Vec2 {
+    let min = CellCoords::from_ivec3(bounds.min).as_game_coordinates() - CELL_SIZE / 2.;
+    let size = bounds.size().as_vec3() * CELL_SIZE;
+    let offset = pixel * (CELL_SIZE / MINIMAP_CELL_PIXELS as f32);
+
+    Vec2::new(min.x + offset.x, min.y + size.y - offset.y)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+fn draw_pixel(pixels: &mut [[u8; 4]], size: IVec2, position: Vec2, color: Color) {
+    let pixel = position.floor().as_ivec2();
+    if pixel.cmpge(IVec2::ZERO).all() && pixel.cmplt(size).all() {
+        if let Some(rgba) = pixels.get_mut((pixel.y * size.x + pixel.x) as usize) {
+            *rgba = color.as_rgba_u8();
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+#[allow(clippy::cast_precision_loss)]
+fn draw_line(pixels: &mut [[u8; 4]], size: IVec2, from: Vec2, to: Vec2, color: Color) {
+    // Footprint corners near the horizon are far away, only the part over the minimap is walked
+    let Some((from, to)) = clip_segment(from, to, size.as_vec2()) else {
+        return;
+    };
+    let steps = (to - from).abs().max_element().ceil().max(1.) as u32;
+    for step in 0..=steps {
+        draw_pixel(
+            pixels,
+            size,
+            from.lerp(to, step as f32 / steps as f32),
+            color,
+        );
+    }
+}
+
+/// Part of the segment inside the rectangle from zero to `max`, Liang-Barsky clipping
+fn clip_segment(from: Vec2, to: Vec2, max: Vec2) -> Option<(Vec2, Vec2)> {
+    let delta = to - from;
+    let mut range = (0_f32, 1_f32);
+    for (direction, distance) in [
+        (-delta.x, from.x),
+        (delta.x, max.x - from.x),
+        (-delta.y, from.y),
+        (delta.y, max.y - from.y),
+    ] {
+        if direction == 0. {
+            if distance < 0. {
+                return None;
+            }
+        } else if direction < 0. {
+            range.0 = range.0.max(distance / direction);
+        } else {
+            range.1 = range.1.min(distance / direction);
+        }
+    }
+
+    (range.0 <= range.1).then(|| (from + delta * range.0, from + delta * range.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_pixels() {
+        let mut cells = Cells::closed(IVec3::new(3, 1, 1));
+        _ = cells.set_face_open(IVec3::ZERO, IVec3::X, true);
+        let pixels = level_pixels(&cells, 0);
+        let pixel = |x: i32, y: i32| {
+            usize::try_from(y * cells.size.x * MINIMAP_CELL_PIXELS + x)
+                .ok()
+                .and_then(|idx| pixels.get(idx))
+                .copied()
+        };
+
+        assert_eq!(pixels.len(), 12 * 4);
+        assert_eq!(pixel(0, 0), Some(WALL_COLOR.as_rgba_u8()));
+        assert_eq!(pixel(0, 1), Some(WALL_COLOR.as_rgba_u8()));
+        assert_eq!(pixel(1, 1), Some(FLOOR_COLOR.as_rgba_u8()));
+        // Open between the first two cells
+        assert_eq!(pixel(3, 1), Some(FLOOR_COLOR.as_rgba_u8()));
+        assert_eq!(pixel(4, 1), Some(FLOOR_COLOR.as_rgba_u8()));
+        assert_eq!(pixel(9, 1), Some(SOLID_COLOR.as_rgba_u8()));
+    }
+
+    #[test]
+    fn test_minimap_pixel_round_trip() {
+        let bounds = IBounds3::new(IVec3::ZERO, IVec3::new(4, 2, 1));
+
+        // Top left corner of the top left cell
+        assert!(minimap_pixel(Vec3::new(-2.5, 0.5, 0.), &bounds).abs_diff_eq(Vec2::ZERO, 1e-4));
+        assert!(
+            minimap_pixel(Vec3::new(1.5, -1.5, 0.), &bounds).abs_diff_eq(Vec2::new(16., 8.), 1e-4)
+        );
+
+        let point = Vec2::new(0.3, -0.7);
+        assert!(
+            minimap_point(minimap_pixel(point.extend(0.), &bounds), &bounds)
+                .abs_diff_eq(point, 1e-4)
+        );
+    }
+
+    #[test]
+    fn test_clip_segment() {
+        let max = Vec2::new(10., 10.);
+
+        assert_eq!(
+            clip_segment(Vec2::new(-10., 5.), Vec2::new(20., 5.), max),
+            Some((Vec2::new(0., 5.), Vec2::new(10., 5.)))
+        );
+        assert_eq!(
+            clip_segment(Vec2::new(2., 3.), Vec2::new(4., 5.), max),
+            Some((Vec2::new(2., 3.), Vec2::new(4., 5.)))
+        );
+        assert_eq!(
+            clip_segment(Vec2::new(-5., -5.), Vec2::new(-1., 20.), max),
+            None
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use core::cmp::Ordering;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::editor_plugin::MapEditor;
+use crate::game_bots_plugin::Bot;
+use crate::input_actions_plugin::ButtonAction;
+
+/// Radius around a bot center a click picks it in
+const BOT_PICK_RADIUS: f32 = 0.3;
+
+/// Picks bots with the cursor: the select action picks the bot under the cursor, or clears the selection
+#[derive(Debug)]
+pub struct PickingPlugin;
+
+impl Plugin for PickingPlugin {
+    fn build(&self, app: &mut App) {
+        _ = app
+            .add_event::<BotClickedEvent>()
+            .init_resource::<SelectedBot>()
+            .add_systems(Update, bot_picking_system);
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct BotClickedEvent {
+    /// `None` when the click missed every bot
+    pub entity: Option<Entity>,
+}
+
+/// Bot the camera and the inspector focus on
+#[derive(Resource, Debug, Default)]
+pub struct SelectedBot(pub Option<Entity>);
+
+/// Ray from the active camera through the mouse cursor, shared by everything picked with the mouse
+#[derive(SystemParam)]
+pub struct CursorRay<'w, 's> {
+    windows_query: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+    ui_interactions: Query<'w, 's, &'static Interaction>,
+}
+
+impl CursorRay<'_, '_> {
+    /// The ray, if the cursor is inside the primary window and not over an interactive UI node
+    pub fn ray(&self) -> Option<Ray3d> {
+        if self
+            .ui_interactions
+            .iter()
+            .any(|&interaction| interaction != Interaction::None)
+        {
+            return None;
+        }
+        let cursor_position = self.windows_query.get_single().ok()?.cursor_position()?;
+        self.cameras_query
+            .iter()
+            .find(|(camera, _)| camera.is_active)
+            .and_then(|(camera, camera_transform)| {
+                camera.viewport_to_world(camera_transform, cursor_position)
+            })
+    }
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn bot_picking_system(
+    button_actions: Res<ButtonInput<ButtonAction>>,
+    map_editor: Option<Res<MapEditor>>,
+    cursor_ray: CursorRay,
+    bots_query: Query<(Entity, &Transform), With<Bot>>,
+    mut selected_bot: ResMut<SelectedBot>,
+    mut bot_clicked_writer: EventWriter<BotClickedEvent>,
+) {
+    // Clicks edit the map while the editor is on
+    if !button_actions.just_pressed(ButtonAction::Select)
+        || map_editor.is_some_and(|map_editor| map_editor.enabled)
+    {
+        return;
+    }
+    let Some(ray) = cursor_ray.ray() else {
+        return;
+    };
+
+    let entity = bots_query
+        .iter()
+        .filter_map(|(entity, transform)| {
+            ray_sphere_distance(ray, transform.translation, BOT_PICK_RADIUS)
+                .map(|distance| (entity, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+        .map(|(entity, _)| entity);
+
+    selected_bot.0 = entity;
+    _ = bot_clicked_writer.send(BotClickedEvent { entity });
+}
+
+/// Distance along the ray to the first hit of a sphere, `None` if the ray misses it or points away
+pub fn ray_sphere_distance(ray: Ray3d, center: Vec3, radius: f32) -> Option<f32> {
+    let to_center = center - ray.origin;
+    let closest_distance = to_center.dot(*ray.direction);
+    let closest_squared = closest_distance.mul_add(-closest_distance, to_center.length_squared());
+    let half_chord_squared = radius.mul_add(radius, -closest_squared);
+    if half_chord_squared < 0. {
+        return None;
+    }
+
+    let distance = closest_distance - half_chord_squared.sqrt();
+    if distance >= 0. {
+        Some(distance)
+    } else {
+        // Inside the sphere
+        (closest_distance + half_chord_squared.sqrt() >= 0.).then_some(0.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ray_sphere_distance() {
+        let ray = Ray3d::new(Vec3::ZERO, Vec3::X);
+
+        assert_eq!(
+            ray_sphere_distance(ray, Vec3::new(5., 0., 0.), 1.),
+            Some(4.)
+        );
+        assert_eq!(ray_sphere_distance(ray, Vec3::new(5., 2., 0.), 1.), None);
+        assert_eq!(ray_sphere_distance(ray, Vec3::new(-5., 0., 0.), 1.), None);
+        assert_eq!(
+            ray_sphere_distance(ray, Vec3::new(0.5, 0., 0.), 1.),
+            Some(0.)
+        );
+    }
+}
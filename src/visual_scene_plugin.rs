@@ -2,6 +2,7 @@ use core::f32::consts::FRAC_PI_2;
 
 use bevy::pbr::light_consts::lumens;
 use bevy::prelude::*;
+use bevy::utils::HashSet;
 use bevy::{
     app::{App, Plugin},
     asset::Assets,
@@ -14,41 +15,72 @@ use bevy::{
 use bevy_xpbd_3d::components::RigidBody;
 use bevy_xpbd_3d::prelude::*;
 
+use crate::game_bots_plugin::Bot;
 use crate::game_cells_plugin::cell;
 use crate::game_cells_plugin::Cells;
 use crate::game_coordinates_utils::{CellCoords, CELL_SIZE};
+use crate::game_items_plugin::Item;
 use crate::game_physics_layers::Layer;
-use crate::game_scene_plugin::CellsSpawnedEvent;
+use crate::game_scene_plugin::{CellsChangedEvent, CellsSpawnedEvent};
 use crate::game_setup_data::MapData;
 use crate::ibounds3::IBounds3;
+use crate::tile_classifier::classify_tile;
+use crate::visual_assets_plugin::VisualAssets;
 
 #[derive(Debug)]
 pub struct VisualSceneElementsPlugin;
 
 impl Plugin for VisualSceneElementsPlugin {
     fn build(&self, app: &mut App) {
-        _ = app.add_systems(Update, create_scene);
+        _ = app.init_resource::<LevelSlice>().add_systems(
+            Update,
+            (create_scene, update_scene_cells, level_slice_system).chain(),
+        );
     }
 }
 
-/// Creates the scene elements (floor, walls, ceiling)
+/// Highest level of cell indices shown, the levels above and the bots and items on them are hidden so the
+/// level can be seen from above. Every level is shown when `None`.
+#[derive(Resource, Debug, Default)]
+pub struct LevelSlice(pub Option<i32>);
+
+impl LevelSlice {
+    fn visibility(&self, level: i32) -> Visibility {
+        if self.0.is_none_or(|slice_level| level <= slice_level) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        }
+    }
+}
+
+/// Creates the scene elements (floor, walls, ceiling), replacing the ones of a previously spawned map
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
 fn create_scene(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    visual_assets: Res<VisualAssets>,
     mut cells_spawned_reader: EventReader<CellsSpawnedEvent>,
     cells: Option<Res<Cells>>,
     map_data: Option<Res<MapData>>,
+    scene_query: Query<Entity, Or<(With<LevelWalls>, With<CellTile>, With<SceneLighting>)>>,
 ) {
     if let Some(cells) = cells {
         if let Some(map_data) = map_data {
             for CellsSpawnedEvent {} in cells_spawned_reader.read() {
+                for entity in &scene_query {
+                    commands.entity(entity).despawn_recursive();
+                }
+
                 spawn_lighting_setup(&mut commands, &mut meshes, &mut materials);
 
                 spawn_scene_cells(
                     &mut commands,
                     &mut meshes,
-                    &mut materials,
+                    &visual_assets,
                     &cells,
                     &map_data.bounds,
                 );
@@ -86,234 +118,273 @@ fn spawn_lighting_setup(
     //     ..default()
     // });
 
-    _ = commands.spawn(PointLightBundle {
-        transform: Transform::from_translation(Vec3::new(8., 8., 8.)),
-        point_light: PointLight {
-            color: Color::RED,
-            range: 64.,
-            // intensity: lumens::LUMENS_PER_LED_WATTS * 100.,
+    _ = commands.spawn((
+        PointLightBundle {
+            transform: Transform::from_translation(Vec3::new(8., 8., 8.)),
+            point_light: PointLight {
+                color: Color::RED,
+                range: 64.,
+                // intensity: lumens::LUMENS_PER_LED_WATTS * 100.,
+                ..default()
+            },
+            ..default()
+        },
+        SceneLighting,
+    ));
+
+    _ = commands.spawn((
+        PointLightBundle {
+            transform: Transform::from_translation(Vec3::new(-8., 8., 8.)),
+            point_light: PointLight {
+                color: Color::GREEN,
+                range: 64.,
+                // intensity: lumens::LUMENS_PER_LED_WATTS * 100.,
+                ..default()
+            },
             ..default()
         },
-        ..default()
-    });
-
-    _ = commands.spawn(PointLightBundle {
-        transform: Transform::from_translation(Vec3::new(-8., 8., 8.)),
-        point_light: PointLight {
-            color: Color::GREEN,
-            range: 64.,
-            // intensity: lumens::LUMENS_PER_LED_WATTS * 100.,
+        SceneLighting,
+    ));
+
+    _ = commands.spawn((
+        PointLightBundle {
+            transform: Transform::from_translation(Vec3::new(8., 8., -8.)),
+            point_light: PointLight {
+                color: Color::BLUE,
+                range: 64.,
+                // intensity: lumens::LUMENS_PER_LED_WATTS * 100.,
+                ..default()
+            },
             ..default()
         },
-        ..default()
-    });
-
-    _ = commands.spawn(PointLightBundle {
-        transform: Transform::from_translation(Vec3::new(8., 8., -8.)),
-        point_light: PointLight {
-            color: Color::BLUE,
-            range: 64.,
-            // intensity: lumens::LUMENS_PER_LED_WATTS * 100.,
+        SceneLighting,
+    ));
+
+    _ = commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(
+                Sphere::new(0.125)
+                    .mesh()
+                    .ico(2)
+                    .expect("Failed to create icosphere"),
+            ),
+            material: materials.add(Color::rgb(0.8, 0.7, 0.6)),
+            transform: Transform::from_translation(Vec3::ZERO),
             ..default()
         },
-        ..default()
-    });
-
-    _ = commands.spawn(PbrBundle {
-        mesh: meshes.add(
-            Sphere::new(0.125)
-                .mesh()
-                .ico(2)
-                .expect("Failed to create icosphere"),
-        ),
-        material: materials.add(Color::rgb(0.8, 0.7, 0.6)),
-        transform: Transform::from_translation(Vec3::ZERO),
-        ..default()
-    });
+        SceneLighting,
+    ));
+}
+
+const WALL_THICKNESS: f32 = 0.1; // Thickness of the wall
+const WALL_MASS_DENSITY_SCALE: f32 = 1.0;
+/// Side in cells of the square chunks the levels are split into, a cell change only rebuilds the walls of its chunk
+const CHUNK_SIZE: i32 = 8;
+
+/// Marks the lights of the scene so they are replaced along with the walls when a map is spawned again
+#[derive(Component, Debug)]
+struct SceneLighting;
+
+/// Marks the merged walls of a chunk of a level so the chunk can be rebuilt when its cells change
+#[derive(Component, Debug)]
+struct LevelWalls {
+    level: i32,
+    chunk: IVec2,
+}
+
+/// Marks the tile model of a cell so it can be replaced when the cell changes
+#[derive(Component, Debug)]
+struct CellTile {
+    cell_indices: IVec3,
+}
+
+/// Axis aligned box in cell indices space, a cell center being at its indices
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct WallBox {
+    center: Vec3,
+    size: Vec3,
+}
+
+/// Boxes of a level with the walls of neighbouring cells merged together
+#[derive(Debug, Default)]
+struct LevelBoxes {
+    /// Walls and floors, solid for the bots
+    walls: Vec<WallBox>,
+    /// Fully closed cells
+    closed: Vec<WallBox>,
 }
 
-#[allow(clippy::cast_possible_wrap)]
-#[allow(clippy::cast_precision_loss)]
 fn spawn_scene_cells(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
+    visual_assets: &VisualAssets,
     cells: &Cells,
     map_bounds: &IBounds3,
 ) {
-    // // let cells_offset = -Vec3::ZERO * CELLS_POSITION_AXIS_ORIENTATION;
-    // let cells_offset = AxisOrientedCellCoords::from_cell_coords(CellCoords::from_cell_indices(
-    //     IVec3::ZERO,
-    //     map_bounds,
-    // ));
-
-    for (level, z) in cells.array.iter().zip(0..) {
-        for (row, y) in level.iter().zip(0..) {
-            for (&cell_type, x) in row.iter().zip(0..) {
-                let cell_coords = CellCoords::from_cell_indices(IVec3::new(x, y, z), map_bounds);
-                // let cell_position = cell_coords_offsetted.as_cell_centered_visual_coordinates();
-                let cell_position = cell_coords.as_game_coordinates();
-                // println!("({x} {y} {z}) => {cell_coords:?} => {cell_position:?}");
-
-                if cell_type == cell::EMPTY {
-                    spawn_closed(commands, meshes, materials, cell_position);
-                } else {
-                    if cell_type.is_closed(cell::OPEN_NEG_X) {
-                        spawn_wall_neg_x(commands, meshes, materials, cell_position);
-                    }
-                    if cell_type.is_closed(cell::OPEN_POS_X) {
-                        spawn_wall_pos_x(commands, meshes, materials, cell_position);
-                    }
-                    if cell_type.is_closed(cell::OPEN_NEG_Y) {
-                        spawn_wall_neg_y(commands, meshes, materials, cell_position);
-                    }
-                    if cell_type.is_closed(cell::OPEN_POS_Y) {
-                        spawn_wall_pos_y(commands, meshes, materials, cell_position);
-                    }
-                    // if cell_type.is_closed(cell::OPEN_POS_Z) {
-                    //     spawn_wall_pos_z(commands, meshes, materials, pos);
-                    // }
-                    if cell_type.is_closed(cell::OPEN_NEG_Z) {
-                        spawn_wall_neg_z(commands, meshes, materials, cell_position);
-                    }
-                }
+    let chunks_count = (cells.size.truncate() + CHUNK_SIZE - 1) / CHUNK_SIZE;
+    for level in 0..cells.size.z {
+        for chunk_y in 0..chunks_count.y {
+            for chunk_x in 0..chunks_count.x {
+                spawn_chunk_walls(
+                    commands,
+                    meshes,
+                    visual_assets,
+                    cells,
+                    level,
+                    IVec2::new(chunk_x, chunk_y),
+                    map_bounds,
+                );
             }
         }
     }
-}
 
-fn spawn_wall_pos_x(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    position: Vec3,
-) {
-    spawn_wall(
-        commands,
-        meshes,
-        materials,
-        position,
-        Quat::from_rotation_y(-1. * FRAC_PI_2),
-    );
+    for cell_indices in cells.indices() {
+        spawn_cell_tile(commands, visual_assets, cells, cell_indices, map_bounds);
+    }
 }
 
-fn spawn_wall_neg_x(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    position: Vec3,
-) {
-    spawn_wall(
-        commands,
-        meshes,
-        materials,
-        position,
-        Quat::from_rotation_y(-3. * FRAC_PI_2),
-    );
+/// Chunk of the level containing the cell
+const fn cell_chunk(cell_indices: IVec3) -> IVec2 {
+    IVec2::new(
+        cell_indices.x.div_euclid(CHUNK_SIZE),
+        cell_indices.y.div_euclid(CHUNK_SIZE),
+    )
 }
 
-fn spawn_wall_pos_y(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    position: Vec3,
+/// Rebuilds the walls of the chunks whose cells changed at runtime (doors, editing), and the tiles of the changed cells
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::too_many_arguments)]
+fn update_scene_cells(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    visual_assets: Res<VisualAssets>,
+    mut cells_changed_reader: EventReader<CellsChangedEvent>,
+    cells: Option<Res<Cells>>,
+    map_data: Option<Res<MapData>>,
+    level_walls_query: Query<(Entity, &LevelWalls)>,
+    cell_tiles_query: Query<(Entity, &CellTile)>,
 ) {
-    spawn_wall(
-        commands,
-        meshes,
-        materials,
-        position,
-        Quat::from_rotation_x(-3. * FRAC_PI_2),
-    );
-}
+    let (Some(cells), Some(map_data)) = (cells, map_data) else {
+        return;
+    };
 
-fn spawn_wall_neg_y(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    position: Vec3,
-) {
-    spawn_wall(
-        commands,
-        meshes,
-        materials,
-        position,
-        Quat::from_rotation_x(-1. * FRAC_PI_2),
-    );
-}
+    let changed_cells = cells_changed_reader
+        .read()
+        .flat_map(|event| event.cell_indices.iter().copied())
+        .collect::<HashSet<_>>();
 
-#[allow(dead_code)]
-fn spawn_wall_pos_z(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    position: Vec3,
-) {
-    spawn_wall(
-        commands,
-        meshes,
-        materials,
-        position,
-        Quat::from_rotation_x(-2. * FRAC_PI_2),
-    );
-}
+    if changed_cells.is_empty() {
+        return;
+    }
 
-fn spawn_wall_neg_z(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    position: Vec3,
-) {
-    spawn_wall(
-        commands,
-        meshes,
-        materials,
-        position,
-        Quat::from_rotation_x(0. * FRAC_PI_2),
-    );
-}
+    for (entity, cell_tile) in &cell_tiles_query {
+        if changed_cells.contains(&cell_tile.cell_indices) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
 
-const WALL_THICKNESS: f32 = 0.1; // Thickness of the wall
-const WALL_MASS_DENSITY_SCALE: f32 = 1.0;
+    for &cell_indices in &changed_cells {
+        spawn_cell_tile(
+            &mut commands,
+            &visual_assets,
+            &cells,
+            cell_indices,
+            &map_data.bounds,
+        );
+    }
 
-fn spawn_closed(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    position: Vec3,
+    let changed_chunks = changed_cells
+        .iter()
+        .map(|&cell_indices| (cell_indices.z, cell_chunk(cell_indices)))
+        .collect::<HashSet<_>>();
+
+    for (entity, level_walls) in &level_walls_query {
+        if changed_chunks.contains(&(level_walls.level, level_walls.chunk)) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for &(level, chunk) in &changed_chunks {
+        spawn_chunk_walls(
+            &mut commands,
+            &mut meshes,
+            &visual_assets,
+            &cells,
+            level,
+            chunk,
+            &map_data.bounds,
+        );
+    }
+}
+
+/// Hides the walls and tiles above the level slice, and the bots and items currently above it
+#[allow(clippy::needless_pass_by_value)]
+#[allow(clippy::type_complexity)]
+fn level_slice_system(
+    level_slice: Res<LevelSlice>,
+    map_data: Option<Res<MapData>>,
+    mut level_walls_query: Query<(&LevelWalls, &mut Visibility)>,
+    mut cell_tiles_query: Query<(&CellTile, &mut Visibility), Without<LevelWalls>>,
+    mut movables_query: Query<
+        (&Transform, &mut Visibility),
+        (
+            Or<(With<Bot>, With<Item>)>,
+            Without<LevelWalls>,
+            Without<CellTile>,
+        ),
+    >,
 ) {
-    _ = commands.spawn(PbrBundle {
-        mesh: meshes.add(Cuboid::from_size(Vec3::splat(CELL_SIZE - WALL_THICKNESS))),
-        material: materials.add(Color::rgb(0.2, 0.1, 0.0)),
-        transform: Transform::from_translation(position),
-        ..default()
-    });
+    let Some(map_data) = map_data else {
+        return;
+    };
+
+    for (level_walls, mut visibility) in &mut level_walls_query {
+        _ = visibility.set_if_neq(level_slice.visibility(level_walls.level));
+    }
+    for (cell_tile, mut visibility) in &mut cell_tiles_query {
+        _ = visibility.set_if_neq(level_slice.visibility(cell_tile.cell_indices.z));
+    }
+    for (transform, mut visibility) in &mut movables_query {
+        let level = CellCoords::from_game_coordinates(transform.translation.round())
+            .as_ivec3()
+            .z
+            - map_data.bounds.min.z;
+        _ = visibility.set_if_neq(level_slice.visibility(level));
+    }
 }
 
-fn spawn_wall(
+/// Spawns the compound collider of a chunk of a level, one mesh for the walls and one for the closed cells that have
+/// no tile model, and the light of the chunk
+#[allow(clippy::cast_precision_loss)]
+fn spawn_chunk_walls(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    position: Vec3,
-    rotation: Quat,
+    visual_assets: &VisualAssets,
+    cells: &Cells,
+    level: i32,
+    chunk: IVec2,
+    map_bounds: &IBounds3,
 ) {
-    let offset = rotation.mul_vec3(Vec3::new(0.0, 0.0, -(0.5 - WALL_THICKNESS)));
-
-    let size = Vec3::new(
-        WALL_THICKNESS.mul_add(-2., CELL_SIZE),
-        WALL_THICKNESS.mul_add(-2., CELL_SIZE),
-        WALL_THICKNESS,
-    );
-    let collider = Collider::cuboid(size.x, size.y, size.z);
-
-    _ = commands
-        .spawn((
-            PbrBundle {
-                mesh: meshes.add(Cuboid::from_size(size)),
-                material: materials.add(Color::rgb(0.9, 0.9, 0.9)),
-                transform: Transform::from_translation(position + offset).with_rotation(rotation),
-                ..default()
-            },
+    let offset = CellCoords::from_cell_indices(IVec3::ZERO, map_bounds).as_game_coordinates();
+
+    let collider_boxes = level_boxes(cells, level, chunk, |_| true);
+    if !collider_boxes.walls.is_empty() {
+        let collider = Collider::compound(
+            collider_boxes
+                .walls
+                .iter()
+                .map(|wall_box| {
+                    (
+                        offset + wall_box.center,
+                        Quat::IDENTITY,
+                        Collider::cuboid(wall_box.size.x, wall_box.size.y, wall_box.size.z),
+                    )
+                })
+                .collect(),
+        );
+
+        _ = commands.spawn((
+            TransformBundle::default(),
             RigidBody::Static,
             MassPropertiesBundle::new_computed(&collider, WALL_MASS_DENSITY_SCALE),
             collider,
@@ -322,16 +393,295 @@ fn spawn_wall(
                 [Layer::Constructed],
                 [Layer::Ground, Layer::Constructed, Layer::Bots],
             ),
-        ))
-        .with_children(|parent| {
-            _ = parent.spawn(PointLightBundle {
+            LevelWalls { level, chunk },
+        ));
+
+        // One light per chunk lights the walls around it
+        let chunk_center =
+            ((chunk * CHUNK_SIZE).as_vec2() + (CHUNK_SIZE - 1) as f32 / 2.) * CELL_SIZE;
+        _ = commands.spawn((
+            PointLightBundle {
                 point_light: PointLight {
                     color: Color::rgb(0.9, 0.8, 1.0),
-                    intensity: lumens::LUMENS_PER_LED_WATTS * 2.,
+                    intensity: lumens::LUMENS_PER_LED_WATTS * 2. * CHUNK_SIZE as f32,
+                    range: CHUNK_SIZE as f32 * CELL_SIZE * 2.,
+                    ..default()
+                },
+                transform: Transform::from_translation(offset + chunk_center.extend(level as f32)),
+                ..default()
+            },
+            LevelWalls { level, chunk },
+        ));
+    }
+
+    let visible_boxes = if visual_assets.tile_scenes.is_empty() {
+        collider_boxes
+    } else {
+        level_boxes(cells, level, chunk, |cell_indices| {
+            tile_scene(visual_assets, cells, cell_indices).is_none()
+        })
+    };
+    for (wall_boxes, material) in [
+        (&visible_boxes.walls, &visual_assets.wall_material),
+        (&visible_boxes.closed, &visual_assets.closed_material),
+    ] {
+        if !wall_boxes.is_empty() {
+            _ = commands.spawn((
+                PbrBundle {
+                    mesh: meshes.add(merged_mesh(wall_boxes, offset)),
+                    material: material.clone(),
                     ..default()
                 },
-                transform: Transform::from_xyz(0.0, 0.0, WALL_THICKNESS),
+                LevelWalls { level, chunk },
+            ));
+        }
+    }
+}
+
+/// Tile model of the cell and its quarter turns, if there is a model for its tile
+fn tile_scene<'a>(
+    visual_assets: &'a VisualAssets,
+    cells: &Cells,
+    cell_indices: IVec3,
+) -> Option<(&'a Handle<Scene>, i32)> {
+    let tile = classify_tile(*cells.get(cell_indices)?);
+    let scene = visual_assets
+        .tile_scenes
+        .get(&(tile.piece, tile.vertical))?;
+    Some((scene, tile.quarter_turns))
+}
+
+/// Spawns the tile model of a cell, if there is one for its tile
+#[allow(clippy::cast_precision_loss)]
+fn spawn_cell_tile(
+    commands: &mut Commands,
+    visual_assets: &VisualAssets,
+    cells: &Cells,
+    cell_indices: IVec3,
+    map_bounds: &IBounds3,
+) {
+    if let Some((scene, quarter_turns)) = tile_scene(visual_assets, cells, cell_indices) {
+        // Tile models are authored Y up like any glTF model
+        let rotation = Quat::from_rotation_z(quarter_turns as f32 * FRAC_PI_2)
+            * Quat::from_rotation_x(FRAC_PI_2);
+        _ = commands.spawn((
+            SceneBundle {
+                scene: scene.clone(),
+                transform: Transform::from_translation(
+                    CellCoords::from_cell_indices(cell_indices, map_bounds).as_game_coordinates(),
+                )
+                .with_rotation(rotation),
                 ..default()
-            });
+            },
+            CellTile { cell_indices },
+        ));
+    }
+}
+
+fn merged_mesh(wall_boxes: &[WallBox], offset: Vec3) -> Mesh {
+    wall_boxes
+        .iter()
+        .map(|wall_box| {
+            Mesh::from(Cuboid::from_size(wall_box.size)).translated_by(offset + wall_box.center)
+        })
+        .reduce(|mut mesh, other| {
+            mesh.merge(other);
+            mesh
+        })
+        .unwrap_or_else(|| Mesh::from(Cuboid::from_size(Vec3::ZERO)))
+}
+
+/// Greedily merges the closed faces of the shown cells of a chunk of a level into as few boxes as possible: side
+/// walls along their row or column, floors and closed cells into rectangles
+#[allow(clippy::cast_precision_loss)]
+fn level_boxes(
+    cells: &Cells,
+    level: i32,
+    chunk: IVec2,
+    is_shown: impl Fn(IVec3) -> bool,
+) -> LevelBoxes {
+    let z = level as f32;
+    let chunk_min = chunk * CHUNK_SIZE;
+    let chunk_size = (chunk_min + CHUNK_SIZE).min(cells.size.truncate()) - chunk_min;
+    let wall_length =
+        |cells_count: i32| WALL_THICKNESS.mul_add(-2., cells_count as f32 * CELL_SIZE);
+    let wall_height = WALL_THICKNESS.mul_add(-2., CELL_SIZE);
+    let face_offset = CELL_SIZE / 2. - WALL_THICKNESS;
+    let rect_center = |min: IVec2, size: IVec2| {
+        ((chunk_min + min).as_vec2() + (size - 1).as_vec2() / 2.) * CELL_SIZE
+    };
+
+    // Cells are indexed from the chunk min corner
+    let is_wall = |x: i32, y: i32, face: cell::Type| {
+        let cell_indices = (chunk_min + IVec2::new(x, y)).extend(level);
+        is_shown(cell_indices)
+            && cells
+                .get(cell_indices)
+                .is_some_and(|&cell_type| cell_type != cell::EMPTY && cell_type.is_closed(face))
+    };
+
+    let mut level_boxes = LevelBoxes::default();
+
+    for x in 0..chunk_size.x {
+        for (face, side) in [(cell::OPEN_NEG_X, -1_f32), (cell::OPEN_POS_X, 1.)] {
+            for (min, size) in greedy_rectangles(IVec2::new(1, chunk_size.y), |indices| {
+                is_wall(x, indices.y, face)
+            }) {
+                let center = rect_center(IVec2::new(x, min.y), size);
+                level_boxes.walls.push(WallBox {
+                    center: Vec3::new(side.mul_add(face_offset, center.x), center.y, z),
+                    size: Vec3::new(WALL_THICKNESS, wall_length(size.y), wall_height),
+                });
+            }
+        }
+    }
+
+    for y in 0..chunk_size.y {
+        for (face, side) in [(cell::OPEN_NEG_Y, -1_f32), (cell::OPEN_POS_Y, 1.)] {
+            for (min, size) in greedy_rectangles(IVec2::new(chunk_size.x, 1), |indices| {
+                is_wall(indices.x, y, face)
+            }) {
+                let center = rect_center(IVec2::new(min.x, y), size);
+                level_boxes.walls.push(WallBox {
+                    center: Vec3::new(center.x, side.mul_add(face_offset, center.y), z),
+                    size: Vec3::new(wall_length(size.x), WALL_THICKNESS, wall_height),
+                });
+            }
+        }
+    }
+
+    for (min, size) in greedy_rectangles(chunk_size, |indices| {
+        is_wall(indices.x, indices.y, cell::OPEN_NEG_Z)
+    }) {
+        level_boxes.walls.push(WallBox {
+            center: rect_center(min, size).extend(z - face_offset),
+            size: Vec3::new(wall_length(size.x), wall_length(size.y), WALL_THICKNESS),
+        });
+    }
+
+    for (min, size) in greedy_rectangles(chunk_size, |indices| {
+        let cell_indices = (chunk_min + indices).extend(level);
+        is_shown(cell_indices) && cells.get(cell_indices) == Some(&cell::EMPTY)
+    }) {
+        level_boxes.closed.push(WallBox {
+            center: rect_center(min, size).extend(z),
+            size: (size.as_vec2() * CELL_SIZE - WALL_THICKNESS).extend(CELL_SIZE - WALL_THICKNESS),
         });
+    }
+
+    level_boxes
+}
+
+/// Covers the set cells of a grid with rectangles, each grown as wide then as tall as possible.
+/// Returns the min corner and size of every rectangle.
+#[allow(clippy::cast_sign_loss)]
+fn greedy_rectangles(size: IVec2, is_set: impl Fn(IVec2) -> bool) -> Vec<(IVec2, IVec2)> {
+    let width = size.x.max(0) as usize;
+    let mut covered = vec![false; width * size.y.max(0) as usize];
+    let covered_idx = |indices: IVec2| indices.y as usize * width + indices.x as usize;
+    let is_free = |covered: &[bool], indices: IVec2| {
+        is_set(indices) && covered.get(covered_idx(indices)) == Some(&false)
+    };
+
+    let mut rectangles = Vec::new();
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let min = IVec2::new(x, y);
+            if !is_free(&covered, min) {
+                continue;
+            }
+
+            let mut rectangle_size = IVec2::ONE;
+            while min.x + rectangle_size.x < size.x
+                && is_free(&covered, min + IVec2::new(rectangle_size.x, 0))
+            {
+                rectangle_size.x += 1;
+            }
+            while min.y + rectangle_size.y < size.y
+                && (0..rectangle_size.x)
+                    .all(|dx| is_free(&covered, min + IVec2::new(dx, rectangle_size.y)))
+            {
+                rectangle_size.y += 1;
+            }
+
+            for dy in 0..rectangle_size.y {
+                for dx in 0..rectangle_size.x {
+                    if let Some(covered) = covered.get_mut(covered_idx(min + IVec2::new(dx, dy))) {
+                        *covered = true;
+                    }
+                }
+            }
+            rectangles.push((min, rectangle_size));
+        }
+    }
+
+    rectangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::cast_sign_loss)]
+    fn test_greedy_rectangles() {
+        let mask = ["##.", "##.", "#.#"];
+        let is_set = |indices: IVec2| {
+            mask.get(indices.y as usize)
+                .and_then(|row| row.as_bytes().get(indices.x as usize))
+                == Some(&b'#')
+        };
+
+        assert_eq!(
+            greedy_rectangles(IVec2::new(3, 3), is_set),
+            [
+                (IVec2::new(0, 0), IVec2::new(2, 2)),
+                (IVec2::new(0, 2), IVec2::new(1, 1)),
+                (IVec2::new(2, 2), IVec2::new(1, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    #[allow(clippy::non_ascii_literal)]
+    fn test_level_boxes_merges_walls() {
+        // A closed 3x1 corridor
+        let cells = Cells::from_string("╞═╡");
+
+        let level_boxes = level_boxes(&cells, 0, IVec2::ZERO, |_| true);
+
+        // Both sides and the floor are merged into one box each, plus the two corridor ends
+        assert_eq!(level_boxes.walls.len(), 5);
+        assert!(level_boxes.closed.is_empty());
+        assert!(level_boxes.walls.contains(&WallBox {
+            center: Vec3::new(1., -(CELL_SIZE / 2. - WALL_THICKNESS), 0.),
+            size: Vec3::new(
+                WALL_THICKNESS.mul_add(-2., 3. * CELL_SIZE),
+                WALL_THICKNESS,
+                WALL_THICKNESS.mul_add(-2., CELL_SIZE)
+            ),
+        }));
+    }
+
+    #[test]
+    #[allow(clippy::non_ascii_literal)]
+    fn test_level_boxes_splits_chunks() {
+        // A closed corridor one cell longer than a chunk
+        let cells = Cells::from_string(&format!("╞{}╡", "═".repeat(8)));
+
+        let first_chunk = level_boxes(&cells, 0, IVec2::ZERO, |_| true);
+        let second_chunk = level_boxes(&cells, 0, IVec2::X, |_| true);
+
+        // The sides and the floor are cut at the chunk edge, the last cell keeps its corridor end
+        assert_eq!(first_chunk.walls.len(), 4);
+        assert_eq!(second_chunk.walls.len(), 4);
+        assert!(second_chunk.walls.contains(&WallBox {
+            center: Vec3::new(8.5 * CELL_SIZE, 0., -(CELL_SIZE / 2. - WALL_THICKNESS)),
+            size: Vec3::new(
+                WALL_THICKNESS.mul_add(-2., 2. * CELL_SIZE),
+                WALL_THICKNESS.mul_add(-2., CELL_SIZE),
+                WALL_THICKNESS
+            ),
+        }));
+    }
 }